@@ -5,6 +5,8 @@ use bytes::Bytes;
 use libipld::Cid;
 use wnfs::common::{BlockStore, BlockStoreError};
 
+use crate::error::WnfsUtilsError;
+
 #[async_trait(?Send)]
 pub trait FFIStore<'a>: FFIStoreClone<'a> {
     async fn get_block(&self, cid: Vec<u8>) -> Result<Vec<u8>>;
@@ -54,7 +56,7 @@ impl<'a> BlockStore for FFIFriendlyBlockStore<'a> {
             .ffi_store
             .get_block(cid.to_bytes())
             .await // Await the async method
-            .map_err(|_| BlockStoreError::CIDNotFound(*cid))?;
+            .map_err(store_error)?;
         Ok(Bytes::copy_from_slice(&bytes))
     }
 
@@ -73,7 +75,7 @@ impl<'a> BlockStore for FFIFriendlyBlockStore<'a> {
                     .await; // Await the async method
                 match result {
                     Ok(_) => Ok(cid.to_owned()),
-                    Err(e) => Err(e),
+                    Err(e) => Err(store_error(e)),
                 }
             }
         }
@@ -84,5 +86,18 @@ impl<'a> BlockStore for FFIFriendlyBlockStore<'a> {
 // Functions
 //--------------------------------------------------------------------------------------------------
 
+// Keeps a missing block as `CIDNotFound` and reports any other failure of the ffi store as a
+// `StoreError`, so that a broken store isn't mistaken for missing data. The original error
+// stays reachable through `downcast_ref`.
+fn store_error(err: anyhow::Error) -> anyhow::Error {
+    match err.downcast_ref::<BlockStoreError>() {
+        Some(BlockStoreError::CIDNotFound(_)) => err,
+        _ => {
+            let reason = err.to_string();
+            err.context(WnfsUtilsError::StoreError(reason))
+        }
+    }
+}
+
 #[cfg(test)]
 mod blockstore_tests;
@@ -1,8 +1,29 @@
-use libipld::{cbor::DagCborCodec, codec::Encode, IpldCodec};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use libipld::{cbor::DagCborCodec, codec::Encode, Cid, IpldCodec};
 
 use wnfs::common::{BlockStore, CODEC_DAG_CBOR};
 
-use crate::{blockstore::FFIFriendlyBlockStore, kvstore::KVBlockStore};
+use crate::error::WnfsUtilsError;
+use crate::memorystore::MemoryBlockStore;
+use crate::{
+    blockstore::{FFIFriendlyBlockStore, FFIStore},
+    kvstore::KVBlockStore,
+};
+
+#[derive(Clone)]
+struct BrokenStore;
+
+#[async_trait(?Send)]
+impl<'a> FFIStore<'a> for BrokenStore {
+    async fn get_block(&self, _cid: Vec<u8>) -> Result<Vec<u8>> {
+        Err(anyhow!("disk unavailable"))
+    }
+
+    async fn put_block(&self, _cid: Vec<u8>, _bytes: Vec<u8>) -> Result<()> {
+        Err(anyhow!("disk unavailable"))
+    }
+}
 
 #[tokio::test]
 async fn inserted_items_can_be_fetched() {
@@ -42,3 +63,21 @@ async fn inserted_items_can_be_fetched() {
     assert_eq!(first_loaded, vec![1, 2, 3, 4, 5]);
     assert_eq!(second_loaded, b"hello world".to_vec());
 }
+
+#[tokio::test]
+async fn store_failures_are_not_missing_blocks() {
+    let blockstore = &mut FFIFriendlyBlockStore::new(Box::new(MemoryBlockStore::new()));
+    let cid = Cid::default();
+    let err: WnfsUtilsError = blockstore.get_block(&cid).await.unwrap_err().into();
+    assert_eq!(err, WnfsUtilsError::BlockNotFound(cid));
+
+    let blockstore = &mut FFIFriendlyBlockStore::new(Box::new(BrokenStore));
+    let err: WnfsUtilsError = blockstore.get_block(&cid).await.unwrap_err().into();
+    assert_eq!(err, WnfsUtilsError::StoreError("disk unavailable".into()));
+    let err: WnfsUtilsError = blockstore
+        .put_block(b"hello".to_vec(), IpldCodec::Raw.into())
+        .await
+        .unwrap_err()
+        .into();
+    assert_eq!(err, WnfsUtilsError::StoreError("disk unavailable".into()));
+}
@@ -0,0 +1,174 @@
+//! Changes between two forest cids of the same drive, e.g. to find out what another device
+//! changed since the last sync.
+
+use futures::StreamExt;
+use libipld::Cid;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use wnfs::private::PrivateNode;
+
+use crate::error::WnfsUtilsError;
+use crate::private_forest::{wnfs_error, PrivateDirectoryHelper};
+use crate::stat::{custom_metadata, NodeKind};
+use crate::walk::WalkOptions;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    /// A file with a new revision, or a directory whose custom metadata changed.
+    Modified,
+}
+
+/// A file or directory that differs between two forest cids.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Change {
+    pub path_segments: Vec<String>,
+    pub change: ChangeKind,
+    pub kind: NodeKind,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Implementations
+//--------------------------------------------------------------------------------------------------
+
+impl<'a> PrivateDirectoryHelper<'a> {
+    /// Lists the files and directories at or below `path_segments` that were added, removed
+    /// or modified between `old_forest_cid` and `new_forest_cid`. Unchanged directories are
+    /// skipped without loading their contents. Every entry of an added or removed directory
+    /// is listed as well.
+    pub async fn diff(
+        &mut self,
+        old_forest_cid: Cid,
+        new_forest_cid: Cid,
+        path_segments: &[String],
+    ) -> Result<Vec<Change>, WnfsUtilsError> {
+        let mut session = self.session();
+        let old = session
+            .load(old_forest_cid)
+            .await
+            .map_err(wnfs_error("diff"))?;
+        let new = session
+            .load(new_forest_cid)
+            .await
+            .map_err(wnfs_error("diff"))?;
+
+        let mut changes = Vec::new();
+        let mut pending = vec![path_segments.to_vec()];
+        while let Some(path) = pending.pop() {
+            let old_node = find_node(&old, &path).await?;
+            let new_node = find_node(&new, &path).await?;
+            match (old_node, new_node) {
+                (None, None) => {}
+                (Some(old_node), None) => {
+                    subtree_changes(&old, path, &old_node, ChangeKind::Removed, &mut changes)
+                        .await?
+                }
+                (None, Some(new_node)) => {
+                    subtree_changes(&new, path, &new_node, ChangeKind::Added, &mut changes).await?
+                }
+                (Some(PrivateNode::File(old_file)), Some(PrivateNode::File(new_file))) => {
+                    if old_file != new_file {
+                        changes.push(Change {
+                            path_segments: path,
+                            change: ChangeKind::Modified,
+                            kind: NodeKind::File,
+                        });
+                    }
+                }
+                (Some(PrivateNode::Dir(old_dir)), Some(PrivateNode::Dir(new_dir))) => {
+                    if old_dir == new_dir {
+                        continue;
+                    }
+                    if custom_metadata(old_dir.get_metadata())
+                        != custom_metadata(new_dir.get_metadata())
+                    {
+                        changes.push(Change {
+                            path_segments: path.to_owned(),
+                            change: ChangeKind::Modified,
+                            kind: NodeKind::Dir,
+                        });
+                    }
+                    let names: BTreeSet<&String> =
+                        old_dir.get_entries().chain(new_dir.get_entries()).collect();
+                    for name in names.into_iter().rev() {
+                        pending.push([path.to_owned(), vec![name.to_owned()]].concat());
+                    }
+                }
+                (Some(old_node), Some(new_node)) => {
+                    subtree_changes(
+                        &old,
+                        path.to_owned(),
+                        &old_node,
+                        ChangeKind::Removed,
+                        &mut changes,
+                    )
+                    .await?;
+                    subtree_changes(&new, path, &new_node, ChangeKind::Added, &mut changes).await?;
+                }
+            }
+        }
+        Ok(changes)
+    }
+
+    pub async fn diff_async(
+        &mut self,
+        old_forest_cid: Cid,
+        new_forest_cid: Cid,
+        path_segments: &[String],
+    ) -> Result<Vec<Change>, WnfsUtilsError> {
+        self.diff(old_forest_cid, new_forest_cid, path_segments)
+            .await
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Functions
+//--------------------------------------------------------------------------------------------------
+
+// Looks up the node at `path_segments`, or `None` if there is none.
+pub(crate) async fn find_node(
+    helper: &PrivateDirectoryHelper<'_>,
+    path_segments: &[String],
+) -> Result<Option<PrivateNode>, WnfsUtilsError> {
+    match helper.get_node(path_segments).await {
+        Ok(node) => Ok(Some(node)),
+        Err(WnfsUtilsError::NotFound(_)) => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+// Records `node` and, for a directory, everything below it as `change`.
+async fn subtree_changes(
+    helper: &PrivateDirectoryHelper<'_>,
+    path_segments: Vec<String>,
+    node: &PrivateNode,
+    change: ChangeKind,
+    changes: &mut Vec<Change>,
+) -> Result<(), WnfsUtilsError> {
+    let kind = match node {
+        PrivateNode::File(_) => NodeKind::File,
+        PrivateNode::Dir(_) => NodeKind::Dir,
+    };
+    changes.push(Change {
+        path_segments: path_segments.to_owned(),
+        change,
+        kind,
+    });
+    if kind == NodeKind::Dir {
+        let mut walk = helper.walk(&path_segments, WalkOptions::default());
+        while let Some(entry) = walk.next().await {
+            let (path_segments, entry) = entry.map_err(wnfs_error("diff"))?;
+            changes.push(Change {
+                path_segments,
+                change,
+                kind: entry.kind,
+            });
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod diff_tests;
@@ -0,0 +1,74 @@
+use crate::blockstore::FFIFriendlyBlockStore;
+use crate::diff::{Change, ChangeKind};
+use crate::memorystore::MemoryBlockStore;
+use crate::private_forest::PrivateDirectoryHelper;
+use crate::stat::NodeKind;
+
+fn change(path: &str, change: ChangeKind, kind: NodeKind) -> Change {
+    Change {
+        path_segments: PrivateDirectoryHelper::parse_path(path.into()),
+        change,
+        kind,
+    }
+}
+
+#[tokio::test]
+async fn diff_lists_changes() {
+    let store = MemoryBlockStore::new();
+    let blockstore = &mut FFIFriendlyBlockStore::new(Box::new(store));
+    let (helper, _, _) = &mut PrivateDirectoryHelper::init(blockstore, vec![0; 32])
+        .await
+        .unwrap();
+    for path in ["root/same.txt", "root/changed.txt", "root/gone/a.txt"] {
+        helper
+            .write_file(
+                &PrivateDirectoryHelper::parse_path(path.into()),
+                b"v1".to_vec(),
+                0,
+            )
+            .await
+            .unwrap();
+    }
+    let old_cid = helper
+        .write_file(&["unrelated.txt".into()], b"v1".to_vec(), 0)
+        .await
+        .unwrap();
+
+    helper
+        .write_file(&["root".into(), "changed.txt".into()], b"v2".to_vec(), 0)
+        .await
+        .unwrap();
+    helper.rm(&["root".into(), "gone".into()]).await.unwrap();
+    let new_cid = helper
+        .write_file(
+            &["root".into(), "new".into(), "b.txt".into()],
+            b"v1".to_vec(),
+            0,
+        )
+        .await
+        .unwrap();
+
+    let changes = helper
+        .diff(old_cid, new_cid, &["root".into()])
+        .await
+        .unwrap();
+    assert_eq!(
+        changes,
+        vec![
+            change("root/changed.txt", ChangeKind::Modified, NodeKind::File),
+            change("root/gone", ChangeKind::Removed, NodeKind::Dir),
+            change("root/gone/a.txt", ChangeKind::Removed, NodeKind::File),
+            change("root/new", ChangeKind::Added, NodeKind::Dir),
+            change("root/new/b.txt", ChangeKind::Added, NodeKind::File),
+        ]
+    );
+
+    let changes = helper.diff(old_cid, old_cid, &[]).await.unwrap();
+    assert!(changes.is_empty());
+
+    let changes = helper.diff(new_cid, old_cid, &[]).await.unwrap();
+    assert!(changes.contains(&change("root/gone", ChangeKind::Added, NodeKind::Dir)));
+    assert!(!changes
+        .iter()
+        .any(|change| change.path_segments == vec!["unrelated.txt".to_string()]));
+}
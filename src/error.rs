@@ -0,0 +1,151 @@
+use libipld::Cid;
+use std::fmt;
+use wnfs::{common::BlockStoreError, error::FsError};
+
+use crate::memorystore::MemoryBlockStoreError;
+
+/// Errors returned by `PrivateDirectoryHelper`. Each variant has a stable numeric code
+/// (see `WnfsUtilsError::code`) for passing across the JS/JNI boundary.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WnfsUtilsError {
+    /// No file or directory exists at the given path.
+    NotFound(String),
+    /// A path segment that must be a directory is a file.
+    NotADirectory,
+    /// The node at the given path is a directory where a file was expected.
+    NotAFile,
+    /// A file or directory already exists at the target path.
+    AlreadyExists,
+    /// The path is empty or otherwise malformed.
+    InvalidPath,
+    /// A block referenced by the forest is missing from the block store.
+    BlockNotFound(Cid),
+    /// The wnfs key is missing or unusable.
+    InvalidKey(String),
+    /// The block store failed to store or retrieve data.
+    StoreError(String),
+    /// No share for this key exists in the forest.
+    ShareNotFound,
+    /// Reading or writing a local file failed.
+    IoError(String),
+    /// An upload was finished before all of its chunks were received. Holds the missing
+    /// chunk indices.
+    IncompleteUpload(Vec<u64>),
+    /// Storing the data would exceed the configured quota.
+    QuotaExceeded {
+        quota_bytes: u64,
+        used_bytes: u64,
+        requested_bytes: u64,
+    },
+    /// The metadata key is maintained by wnfs and can't be set or removed.
+    ReservedMetadataKey(String),
+    /// Both sides of a merge changed the same paths in different ways.
+    MergeConflict(Vec<String>),
+    /// The helper was opened read-only, e.g. for a received share.
+    ReadOnly,
+    /// Any other wnfs error.
+    Other(String),
+}
+
+//--------------------------------------------------------------------------------------------------
+// Implementations
+//--------------------------------------------------------------------------------------------------
+
+impl WnfsUtilsError {
+    /// Stable numeric code of the error. Codes are never reused or renumbered.
+    pub fn code(&self) -> u32 {
+        match self {
+            WnfsUtilsError::NotFound(_) => 1,
+            WnfsUtilsError::NotADirectory => 2,
+            WnfsUtilsError::NotAFile => 3,
+            WnfsUtilsError::AlreadyExists => 4,
+            WnfsUtilsError::InvalidPath => 5,
+            WnfsUtilsError::BlockNotFound(_) => 6,
+            WnfsUtilsError::InvalidKey(_) => 7,
+            WnfsUtilsError::StoreError(_) => 8,
+            WnfsUtilsError::ShareNotFound => 9,
+            WnfsUtilsError::IoError(_) => 10,
+            WnfsUtilsError::IncompleteUpload(_) => 11,
+            WnfsUtilsError::QuotaExceeded { .. } => 12,
+            WnfsUtilsError::ReservedMetadataKey(_) => 13,
+            WnfsUtilsError::MergeConflict(_) => 14,
+            WnfsUtilsError::ReadOnly => 15,
+            WnfsUtilsError::Other(_) => 99,
+        }
+    }
+}
+
+impl fmt::Display for WnfsUtilsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WnfsUtilsError::NotFound(path) => write!(f, "not found: {}", path),
+            WnfsUtilsError::NotADirectory => write!(f, "not a directory"),
+            WnfsUtilsError::NotAFile => write!(f, "not a file"),
+            WnfsUtilsError::AlreadyExists => write!(f, "already exists"),
+            WnfsUtilsError::InvalidPath => write!(f, "invalid path"),
+            WnfsUtilsError::BlockNotFound(cid) => write!(f, "block not found: {}", cid),
+            WnfsUtilsError::InvalidKey(reason) => write!(f, "invalid wnfs key: {}", reason),
+            WnfsUtilsError::StoreError(reason) => write!(f, "store error: {}", reason),
+            WnfsUtilsError::ShareNotFound => write!(f, "share not found"),
+            WnfsUtilsError::IoError(reason) => write!(f, "io error: {}", reason),
+            WnfsUtilsError::IncompleteUpload(missing) => {
+                write!(f, "incomplete upload, missing chunks: {:?}", missing)
+            }
+            WnfsUtilsError::QuotaExceeded {
+                quota_bytes,
+                used_bytes,
+                requested_bytes,
+            } => write!(
+                f,
+                "quota exceeded: {} of {} bytes used, {} bytes requested",
+                used_bytes, quota_bytes, requested_bytes
+            ),
+            WnfsUtilsError::ReservedMetadataKey(key) => {
+                write!(f, "reserved metadata key: {}", key)
+            }
+            WnfsUtilsError::MergeConflict(paths) => {
+                write!(f, "merge conflict: {}", paths.join(", "))
+            }
+            WnfsUtilsError::ReadOnly => write!(f, "read-only"),
+            WnfsUtilsError::Other(reason) => write!(f, "{}", reason),
+        }
+    }
+}
+
+impl std::error::Error for WnfsUtilsError {}
+
+impl From<anyhow::Error> for WnfsUtilsError {
+    fn from(err: anyhow::Error) -> Self {
+        if let Some(err) = err.downcast_ref::<WnfsUtilsError>() {
+            return err.to_owned();
+        }
+        if let Some(fs_error) = err.downcast_ref::<FsError>() {
+            return match fs_error {
+                FsError::NotFound => WnfsUtilsError::NotFound(String::new()),
+                FsError::NotADirectory => WnfsUtilsError::NotADirectory,
+                FsError::NotAFile => WnfsUtilsError::NotAFile,
+                FsError::FileAlreadyExists | FsError::DirectoryAlreadyExists => {
+                    WnfsUtilsError::AlreadyExists
+                }
+                FsError::InvalidPath => WnfsUtilsError::InvalidPath,
+                _ => WnfsUtilsError::Other(err.to_string()),
+            };
+        }
+        if let Some(BlockStoreError::CIDNotFound(cid)) = err.downcast_ref::<BlockStoreError>() {
+            return WnfsUtilsError::BlockNotFound(*cid);
+        }
+        if let Some(err) = err.downcast_ref::<MemoryBlockStoreError>() {
+            return WnfsUtilsError::StoreError(err.to_string());
+        }
+        WnfsUtilsError::Other(err.to_string())
+    }
+}
+
+impl From<std::io::Error> for WnfsUtilsError {
+    fn from(err: std::io::Error) -> Self {
+        WnfsUtilsError::IoError(err.to_string())
+    }
+}
+
+#[cfg(test)]
+mod error_tests;
@@ -0,0 +1,34 @@
+use libipld::Cid;
+use wnfs::{common::BlockStoreError, error::FsError};
+
+use crate::error::WnfsUtilsError;
+use crate::memorystore::MemoryBlockStoreError;
+
+#[test]
+fn wnfs_errors_are_classified() {
+    let err: WnfsUtilsError = anyhow::Error::from(FsError::NotFound).into();
+    assert_eq!(err.code(), 1);
+
+    let err: WnfsUtilsError = anyhow::Error::from(FsError::NotADirectory).into();
+    assert_eq!(err, WnfsUtilsError::NotADirectory);
+
+    let cid = Cid::default();
+    let err: WnfsUtilsError = anyhow::Error::from(BlockStoreError::CIDNotFound(cid)).into();
+    assert_eq!(err, WnfsUtilsError::BlockNotFound(cid));
+    assert_eq!(err.code(), 6);
+}
+
+#[test]
+fn store_errors_are_classified() {
+    let err: WnfsUtilsError = anyhow::Error::from(MemoryBlockStoreError::StoreFull {
+        capacity_bytes: 1,
+        used_bytes: 1,
+        requested_bytes: 1,
+    })
+    .into();
+    assert_eq!(err.code(), 8);
+
+    let err: WnfsUtilsError = anyhow::anyhow!("something else").into();
+    assert_eq!(err, WnfsUtilsError::Other("something else".into()));
+    assert_eq!(err.code(), 99);
+}
@@ -0,0 +1,167 @@
+//! Browsing and reading previous revisions of files and directories. Every write creates a
+//! new revision of the changed node and its ancestors; older revisions stay in the forest
+//! and are found by stepping back through the node's ratchet. Revisions are identified by
+//! the cid of their encrypted content block, which doesn't change when new revisions are
+//! written.
+
+use rand::thread_rng;
+use serde::{Deserialize, Serialize};
+use std::rc::Rc;
+use wnfs::private::{PrivateNode, PrivateNodeOnPathHistory};
+
+use crate::error::WnfsUtilsError;
+use crate::private_forest::{wnfs_error, PrivateDirectoryHelper};
+use crate::stat::NodeKind;
+
+// How many ratchet steps to search for a previous revision before giving up.
+const DISCREPANCY_BUDGET: usize = 1_000_000;
+
+/// A revision of a file or directory.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Revision {
+    /// Cid of the encrypted content block of the revision, which identifies it.
+    pub revision: String,
+    pub kind: NodeKind,
+    /// Size of the file content in bytes, `None` for directories.
+    pub size: Option<u64>,
+    /// Modification time in seconds since the unix epoch.
+    pub modified: Option<i64>,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Implementations
+//--------------------------------------------------------------------------------------------------
+
+impl<'a> PrivateDirectoryHelper<'a> {
+    /// Lists up to `limit` revisions of the file or directory at `path_segments`, starting
+    /// with the current one.
+    pub async fn history(
+        &mut self,
+        path_segments: &[String],
+        limit: usize,
+    ) -> Result<Vec<Revision>, WnfsUtilsError> {
+        let mut revisions = Vec::new();
+        if limit == 0 {
+            return Ok(revisions);
+        }
+        let current = self
+            .get_node(path_segments)
+            .await
+            .map_err(wnfs_error("history"))?;
+        revisions.push(self.revision(&current).await?);
+        let mut history = self.node_history(path_segments).await?;
+        while revisions.len() < limit {
+            let Some(node) = history
+                .get_previous(&self.store)
+                .await
+                .map_err(wnfs_error("history"))?
+            else {
+                break;
+            };
+            revisions.push(self.revision(&node).await?);
+        }
+        Ok(revisions)
+    }
+
+    /// Reads the content of the file at `path_segments` as it was at `revision`, as listed
+    /// by `history`.
+    pub async fn read_file_at_revision(
+        &mut self,
+        path_segments: &[String],
+        revision: &str,
+    ) -> Result<Vec<u8>, WnfsUtilsError> {
+        let node = self.node_at_revision(path_segments, revision).await?;
+        let file = node
+            .as_file()
+            .map_err(wnfs_error("read_file_at_revision"))?;
+        file.get_content(&self.forest, &self.store)
+            .await
+            .map_err(wnfs_error("read_file_at_revision"))
+    }
+
+    // Looks up the node at `path_segments` as it was at `revision`.
+    pub(crate) async fn node_at_revision(
+        &self,
+        path_segments: &[String],
+        revision: &str,
+    ) -> Result<PrivateNode, WnfsUtilsError> {
+        let mut node = self
+            .get_node(path_segments)
+            .await
+            .map_err(wnfs_error("node_at_revision"))?;
+        let mut history = self.node_history(path_segments).await?;
+        while self.revision_cid(&node).await? != revision {
+            node = history
+                .get_previous(&self.store)
+                .await
+                .map_err(wnfs_error("node_at_revision"))?
+                .ok_or_else(|| {
+                    WnfsUtilsError::NotFound(format!(
+                        "{} at revision {}",
+                        path_segments.join("/"),
+                        revision
+                    ))
+                })
+                .map_err(wnfs_error("node_at_revision"))?;
+        }
+        Ok(node)
+    }
+
+    async fn node_history(
+        &self,
+        path_segments: &[String],
+    ) -> Result<PrivateNodeOnPathHistory, WnfsUtilsError> {
+        PrivateNodeOnPathHistory::of(
+            Rc::clone(&self.root_dir),
+            Rc::clone(&self.first_root_dir),
+            DISCREPANCY_BUDGET,
+            path_segments,
+            true,
+            Rc::clone(&self.forest),
+            &self.store,
+        )
+        .await
+        .map_err(wnfs_error("history"))
+    }
+
+    async fn revision(&self, node: &PrivateNode) -> Result<Revision, WnfsUtilsError> {
+        let stat = self.node_stat(node).await?;
+        Ok(Revision {
+            revision: self.revision_cid(node).await?,
+            kind: stat.kind,
+            size: stat.size,
+            modified: stat.modified,
+        })
+    }
+
+    // Cid of the encrypted content block of `node`. Nodes read from the forest are already
+    // stored, so storing them again only yields their access key. It is done on a copy of the
+    // forest, which is dropped.
+    async fn revision_cid(&self, node: &PrivateNode) -> Result<String, WnfsUtilsError> {
+        let mut forest = Rc::clone(&self.forest);
+        let access_key = node
+            .store(&mut forest, &self.store, &mut thread_rng())
+            .await
+            .map_err(wnfs_error("revision_cid"))?;
+        Ok(access_key.get_content_cid().to_string())
+    }
+
+    pub async fn history_async(
+        &mut self,
+        path_segments: &[String],
+        limit: usize,
+    ) -> Result<Vec<Revision>, WnfsUtilsError> {
+        self.history(path_segments, limit).await
+    }
+
+    pub async fn read_file_at_revision_async(
+        &mut self,
+        path_segments: &[String],
+        revision: &str,
+    ) -> Result<Vec<u8>, WnfsUtilsError> {
+        self.read_file_at_revision(path_segments, revision).await
+    }
+}
+
+#[cfg(test)]
+mod history_tests;
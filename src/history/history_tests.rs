@@ -0,0 +1,78 @@
+use crate::blockstore::FFIFriendlyBlockStore;
+use crate::error::WnfsUtilsError;
+use crate::memorystore::MemoryBlockStore;
+use crate::private_forest::PrivateDirectoryHelper;
+use crate::stat::NodeKind;
+
+#[tokio::test]
+async fn history_of_overwritten_file() {
+    let store = MemoryBlockStore::new();
+    let blockstore = &mut FFIFriendlyBlockStore::new(Box::new(store));
+    let (helper, _, _) = &mut PrivateDirectoryHelper::init(blockstore, vec![0; 32])
+        .await
+        .unwrap();
+    let path = ["root".to_string(), "doc.txt".to_string()];
+    helper
+        .write_file(&path, b"first".to_vec(), 1_700_000_000)
+        .await
+        .unwrap();
+    helper
+        .write_file(&path, b"second!".to_vec(), 1_700_000_100)
+        .await
+        .unwrap();
+    let cid = helper
+        .write_file(&path, b"oops".to_vec(), 1_700_000_200)
+        .await
+        .unwrap();
+
+    // History survives reopening the drive.
+    let helper = &mut PrivateDirectoryHelper::load_with_wnfs_key(blockstore, cid, vec![0; 32])
+        .await
+        .unwrap();
+    let revisions = helper.history(&path, 10).await.unwrap();
+    assert_eq!(
+        revisions
+            .iter()
+            .map(|revision| (revision.kind, revision.size, revision.modified))
+            .collect::<Vec<_>>(),
+        vec![
+            (NodeKind::File, Some(4), Some(1_700_000_200)),
+            (NodeKind::File, Some(7), Some(1_700_000_100)),
+            (NodeKind::File, Some(5), Some(1_700_000_000)),
+        ]
+    );
+
+    let content = helper
+        .read_file_at_revision(&path, &revisions[1].revision)
+        .await
+        .unwrap();
+    assert_eq!(content, b"second!".to_vec());
+    let content = helper
+        .read_file_at_revision(&path, &revisions[0].revision)
+        .await
+        .unwrap();
+    assert_eq!(content, b"oops".to_vec());
+
+    // Revisions keep their identifiers when new revisions are written.
+    helper
+        .write_file(&path, b"fixed".to_vec(), 1_700_000_300)
+        .await
+        .unwrap();
+    let later_revisions = helper.history(&path, 10).await.unwrap();
+    assert_eq!(later_revisions.len(), 4);
+    assert_eq!(later_revisions[1..], revisions[..]);
+    let content = helper
+        .read_file_at_revision(&path, &revisions[1].revision)
+        .await
+        .unwrap();
+    assert_eq!(content, b"second!".to_vec());
+
+    let revisions = helper.history(&path, 1).await.unwrap();
+    assert_eq!(revisions.len(), 1);
+
+    let err = helper
+        .read_file_at_revision(&path, "missing")
+        .await
+        .unwrap_err();
+    assert!(matches!(err, WnfsUtilsError::NotFound(_)));
+}
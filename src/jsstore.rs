@@ -0,0 +1,78 @@
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use js_sys::{Function, Promise, Uint8Array};
+use libipld::Cid;
+use wasm_bindgen::JsValue;
+use wasm_bindgen_futures::JsFuture;
+use wnfs::common::BlockStoreError;
+
+use crate::blockstore::FFIStore;
+
+/// Block store backed by host-supplied JS functions, e.g. IndexedDB, a fetch-based gateway
+/// or an in-memory `Map`.
+///
+/// `get_block(cid: Uint8Array): Promise<Uint8Array>`
+/// `put_block(cid: Uint8Array, bytes: Uint8Array): Promise<void>`
+///
+/// `get_block` resolves to `null` or `undefined` for a missing block. Rejections of either
+/// function are reported as store errors.
+#[derive(Clone)]
+pub struct JsBlockStore {
+    pub get_block_fn: Function,
+    pub put_block_fn: Function,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Implementations
+//--------------------------------------------------------------------------------------------------
+
+impl JsBlockStore {
+    /// Creates a new js block store.
+    pub fn new(get_block_fn: Function, put_block_fn: Function) -> Self {
+        Self {
+            get_block_fn,
+            put_block_fn,
+        }
+    }
+}
+
+#[async_trait(?Send)]
+impl<'a> FFIStore<'a> for JsBlockStore {
+    /// Retrieves an array of bytes from the block store with given CID.
+    async fn get_block(&self, cid: Vec<u8>) -> Result<Vec<u8>> {
+        let js_cid = Uint8Array::from(cid.as_slice());
+        let result = self
+            .get_block_fn
+            .call1(&JsValue::NULL, &js_cid.into())
+            .map_err(to_anyhow)?;
+        let bytes = JsFuture::from(Promise::resolve(&result))
+            .await
+            .map_err(to_anyhow)?;
+        if bytes.is_null() || bytes.is_undefined() {
+            return Err(BlockStoreError::CIDNotFound(Cid::try_from(cid)?).into());
+        }
+        Ok(Uint8Array::new(&bytes).to_vec())
+    }
+
+    /// Stores an array of bytes in the block store.
+    async fn put_block(&self, cid: Vec<u8>, bytes: Vec<u8>) -> Result<()> {
+        let cid = Uint8Array::from(cid.as_slice());
+        let bytes = Uint8Array::from(bytes.as_slice());
+        let result = self
+            .put_block_fn
+            .call2(&JsValue::NULL, &cid.into(), &bytes.into())
+            .map_err(to_anyhow)?;
+        JsFuture::from(Promise::resolve(&result))
+            .await
+            .map_err(to_anyhow)?;
+        Ok(())
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Functions
+//--------------------------------------------------------------------------------------------------
+
+fn to_anyhow(err: JsValue) -> anyhow::Error {
+    anyhow!("{:?}", err)
+}
@@ -0,0 +1,147 @@
+//! Derives the 32 byte wnfs key from secrets of arbitrary length, such as a passphrase or a
+//! wallet signature. The parameters used for the derivation are kept in a versioned record
+//! in the block store, so the same key can be derived again when the drive is reopened.
+
+use argon2::{Algorithm, Argon2, Params, Version};
+use hkdf::Hkdf;
+use libipld::Cid;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha3::Sha3_256;
+use wnfs::common::BlockStore;
+
+use crate::blockstore::FFIFriendlyBlockStore;
+use crate::error::WnfsUtilsError;
+use crate::private_forest::{WnfsSession, WNFS_KEY_LENGTH};
+
+/// Current version of the `KdfParams` record.
+pub const KDF_PARAMS_VERSION: u32 = 1;
+
+const SALT_LENGTH: usize = 16;
+const HKDF_INFO: &[u8] = b"wnfsutils/wnfs-key/v1";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum KdfAlgorithm {
+    /// For high-entropy secrets such as wallet signatures.
+    HkdfSha3_256,
+    /// For low-entropy secrets such as passphrases.
+    Argon2id {
+        m_cost: u32,
+        t_cost: u32,
+        p_cost: u32,
+    },
+}
+
+/// Versioned parameters of a wnfs key derivation, stored next to the forest.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KdfParams {
+    pub version: u32,
+    pub algorithm: KdfAlgorithm,
+    pub salt: Vec<u8>,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Implementations
+//--------------------------------------------------------------------------------------------------
+
+impl KdfParams {
+    /// Creates new parameters with a random salt.
+    pub fn new(algorithm: KdfAlgorithm) -> Self {
+        let mut salt = vec![0u8; SALT_LENGTH];
+        rand::thread_rng().fill_bytes(&mut salt);
+        Self {
+            version: KDF_PARAMS_VERSION,
+            algorithm,
+            salt,
+        }
+    }
+
+    /// Creates new Argon2id parameters with the crate's default costs.
+    pub fn argon2id() -> Self {
+        Self::new(KdfAlgorithm::Argon2id {
+            m_cost: Params::DEFAULT_M_COST,
+            t_cost: Params::DEFAULT_T_COST,
+            p_cost: Params::DEFAULT_P_COST,
+        })
+    }
+
+    /// Creates new HKDF-SHA3-256 parameters.
+    pub fn hkdf() -> Self {
+        Self::new(KdfAlgorithm::HkdfSha3_256)
+    }
+
+    /// Derives the wnfs key from `secret`.
+    pub fn derive_wnfs_key(&self, secret: &[u8]) -> Result<Vec<u8>, WnfsUtilsError> {
+        if secret.is_empty() {
+            return Err(WnfsUtilsError::InvalidKey("secret is empty".into()));
+        }
+        if self.version != KDF_PARAMS_VERSION {
+            return Err(WnfsUtilsError::InvalidKey(format!(
+                "unsupported kdf params version: {}",
+                self.version
+            )));
+        }
+        let mut wnfs_key = vec![0u8; WNFS_KEY_LENGTH];
+        match &self.algorithm {
+            KdfAlgorithm::HkdfSha3_256 => {
+                Hkdf::<Sha3_256>::new(Some(&self.salt), secret)
+                    .expand(HKDF_INFO, &mut wnfs_key)
+                    .map_err(|e| WnfsUtilsError::InvalidKey(e.to_string()))?;
+            }
+            KdfAlgorithm::Argon2id {
+                m_cost,
+                t_cost,
+                p_cost,
+            } => {
+                let params = Params::new(*m_cost, *t_cost, *p_cost, Some(WNFS_KEY_LENGTH))
+                    .map_err(|e| WnfsUtilsError::InvalidKey(e.to_string()))?;
+                Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+                    .hash_password_into(secret, &self.salt, &mut wnfs_key)
+                    .map_err(|e| WnfsUtilsError::InvalidKey(e.to_string()))?;
+            }
+        }
+        Ok(wnfs_key)
+    }
+
+    /// Stores the parameters record in the block store and returns its cid.
+    pub async fn store(&self, store: &FFIFriendlyBlockStore<'_>) -> Result<Cid, WnfsUtilsError> {
+        Ok(store.put_serializable(self).await?)
+    }
+
+    /// Loads a parameters record from the block store.
+    pub async fn load(
+        store: &FFIFriendlyBlockStore<'_>,
+        cid: &Cid,
+    ) -> Result<KdfParams, WnfsUtilsError> {
+        Ok(store.get_deserializable::<KdfParams>(cid).await?)
+    }
+}
+
+impl<'a> WnfsSession<'a> {
+    /// Creates a session whose wnfs key is derived from `secret` with new `params`.
+    /// Returns the session and the cid of the stored parameters, which must be kept
+    /// next to the forest cid to open the drive again.
+    pub async fn with_secret(
+        store: FFIFriendlyBlockStore<'a>,
+        secret: &[u8],
+        params: KdfParams,
+    ) -> Result<(WnfsSession<'a>, Cid), WnfsUtilsError> {
+        let wnfs_key = params.derive_wnfs_key(secret)?;
+        let params_cid = params.store(&store).await?;
+        Ok((WnfsSession::new(store, wnfs_key), params_cid))
+    }
+
+    /// Reopens a session created with `with_secret` using the stored parameters at `params_cid`.
+    pub async fn open_with_secret(
+        store: FFIFriendlyBlockStore<'a>,
+        secret: &[u8],
+        params_cid: &Cid,
+    ) -> Result<WnfsSession<'a>, WnfsUtilsError> {
+        let params = KdfParams::load(&store, params_cid).await?;
+        let wnfs_key = params.derive_wnfs_key(secret)?;
+        Ok(WnfsSession::new(store, wnfs_key))
+    }
+}
+
+#[cfg(test)]
+mod kdf_tests;
@@ -0,0 +1,84 @@
+use crate::blockstore::FFIFriendlyBlockStore;
+use crate::error::WnfsUtilsError;
+use crate::kdf::{KdfAlgorithm, KdfParams};
+use crate::memorystore::MemoryBlockStore;
+use crate::private_forest::{PrivateDirectoryHelper, WnfsSession};
+
+fn test_argon2id_params() -> KdfParams {
+    KdfParams::new(KdfAlgorithm::Argon2id {
+        m_cost: 64,
+        t_cost: 1,
+        p_cost: 1,
+    })
+}
+
+#[test]
+fn derivation_is_deterministic_per_params() {
+    for params in [KdfParams::hkdf(), test_argon2id_params()] {
+        let key1 = params
+            .derive_wnfs_key(b"correct horse battery staple")
+            .unwrap();
+        let key2 = params
+            .derive_wnfs_key(b"correct horse battery staple")
+            .unwrap();
+        assert_eq!(key1.len(), 32);
+        assert_eq!(key1, key2);
+
+        let other_key = params.derive_wnfs_key(b"another secret").unwrap();
+        assert_ne!(key1, other_key);
+    }
+
+    let key1 = KdfParams::hkdf().derive_wnfs_key(b"secret").unwrap();
+    let key2 = KdfParams::hkdf().derive_wnfs_key(b"secret").unwrap();
+    assert_ne!(key1, key2, "fresh params must use a fresh salt");
+}
+
+#[test]
+fn empty_secret_is_rejected() {
+    let err = KdfParams::hkdf().derive_wnfs_key(b"").unwrap_err();
+    assert!(matches!(err, WnfsUtilsError::InvalidKey(_)));
+}
+
+#[tokio::test]
+async fn wrong_key_length_is_rejected() {
+    let store = MemoryBlockStore::new();
+    let blockstore = &mut FFIFriendlyBlockStore::new(Box::new(store));
+    let err = PrivateDirectoryHelper::init(blockstore, vec![0; 31])
+        .await
+        .err()
+        .unwrap();
+    assert!(matches!(err, WnfsUtilsError::InvalidKey(_)));
+}
+
+#[tokio::test]
+async fn session_can_be_reopened_with_secret() {
+    let store = MemoryBlockStore::new();
+    let secret = b"a wallet signature of any length".to_vec();
+
+    let (session, params_cid) = &mut WnfsSession::with_secret(
+        FFIFriendlyBlockStore::new(Box::new(store.to_owned())),
+        &secret,
+        test_argon2id_params(),
+    )
+    .await
+    .unwrap();
+    let (helper, _, _) = &mut session.init().await.unwrap();
+    let cid = helper
+        .write_file(&["root".into(), "a.txt".into()], b"a".to_vec(), 0)
+        .await
+        .unwrap();
+
+    let session = &mut WnfsSession::open_with_secret(
+        FFIFriendlyBlockStore::new(Box::new(store.to_owned())),
+        &secret,
+        params_cid,
+    )
+    .await
+    .unwrap();
+    let helper = &mut session.load(cid).await.unwrap();
+    let content = helper
+        .read_file(&["root".into(), "a.txt".into()])
+        .await
+        .unwrap();
+    assert_eq!(content, b"a".to_vec());
+}
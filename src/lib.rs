@@ -1,3 +1,24 @@
 pub mod blockstore;
+pub mod diff;
+pub mod error;
+pub mod history;
+#[cfg(target_arch = "wasm32")]
+pub mod jsstore;
+pub mod kdf;
 pub mod kvstore;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod localfs;
+pub mod memorystore;
+pub mod merge;
+pub mod metadata;
 pub mod private_forest;
+pub mod restore;
+pub mod share;
+pub mod stat;
+mod state;
+pub mod transaction;
+pub mod upload;
+pub mod usage;
+pub mod walk;
+#[cfg(target_arch = "wasm32")]
+pub mod web;
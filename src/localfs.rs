@@ -0,0 +1,232 @@
+//! Importing and exporting whole directories between the local filesystem and the private drive.
+
+use chrono::{DateTime, Utc};
+use libipld::Cid;
+use std::{
+    path::{Component, Path, PathBuf},
+    time::SystemTime,
+};
+use wnfs::private::PrivateNode;
+
+use crate::error::WnfsUtilsError;
+use crate::private_forest::{wnfs_error, PrivateDirectoryHelper};
+
+/// Progress reported after each directory or file of an import.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportProgress {
+    pub path_segments: Vec<String>,
+    pub files_done: usize,
+    pub files_total: usize,
+    pub bytes_done: u64,
+    pub bytes_total: u64,
+}
+
+enum LocalEntry {
+    Dir {
+        path_segments: Vec<String>,
+    },
+    File {
+        local_path: PathBuf,
+        path_segments: Vec<String>,
+        size: u64,
+        modification_time_seconds: i64,
+    },
+}
+
+//--------------------------------------------------------------------------------------------------
+// Implementations
+//--------------------------------------------------------------------------------------------------
+
+impl<'a> PrivateDirectoryHelper<'a> {
+    /// Recursively imports the local directory `local_path` into `target_path_segments`,
+    /// creating the matching private directories and streaming file contents. File
+    /// modification times are preserved. The forest is committed once at the end, so a
+    /// failed import leaves the drive unchanged.
+    pub async fn import_dir(
+        &mut self,
+        local_path: &String,
+        target_path_segments: &[String],
+        mut progress: impl FnMut(&ImportProgress),
+    ) -> Result<Cid, WnfsUtilsError> {
+        self.check_writable()?;
+        let entries = scan_local_dir(Path::new(local_path), target_path_segments)
+            .map_err(wnfs_error("import_dir"))?;
+        let mut import_progress = ImportProgress {
+            path_segments: target_path_segments.to_vec(),
+            files_done: 0,
+            files_total: 0,
+            bytes_done: 0,
+            bytes_total: 0,
+        };
+        for entry in entries.iter() {
+            if let LocalEntry::File { size, .. } = entry {
+                import_progress.files_total += 1;
+                import_progress.bytes_total += size;
+            }
+        }
+
+        let mut transaction = self.transaction();
+        transaction.mkdir(target_path_segments).await?;
+        for entry in entries {
+            match entry {
+                LocalEntry::Dir { path_segments } => {
+                    transaction.mkdir(&path_segments).await?;
+                    import_progress.path_segments = path_segments;
+                }
+                LocalEntry::File {
+                    local_path,
+                    path_segments,
+                    size,
+                    modification_time_seconds,
+                } => {
+                    let file = async_std::fs::File::open(&local_path)
+                        .await
+                        .map_err(wnfs_error("import_dir"))?;
+                    transaction
+                        .write_file_stream(&path_segments, file, modification_time_seconds)
+                        .await?;
+                    import_progress.path_segments = path_segments;
+                    import_progress.files_done += 1;
+                    import_progress.bytes_done += size;
+                }
+            }
+            progress(&import_progress);
+        }
+        transaction.commit().await
+    }
+
+    /// Recursively exports the private directory `source_path_segments` into the local
+    /// directory `local_path`, recreating directories, streaming file contents and setting
+    /// file modification times from the wnfs metadata. Returns the number of files exported.
+    /// Fails with `InvalidPath` on entry names that aren't a single plain path component,
+    /// like `..` or `a/b`, which would write outside `local_path`.
+    pub async fn export_dir(
+        &mut self,
+        source_path_segments: &[String],
+        local_path: &String,
+    ) -> Result<usize, WnfsUtilsError> {
+        let source_dir = self
+            .get_node(source_path_segments)
+            .await
+            .map_err(wnfs_error("export_dir"))?
+            .as_dir()
+            .map_err(wnfs_error("export_dir"))?;
+        let mut files_exported = 0;
+        let mut stack = vec![(source_dir, PathBuf::from(local_path))];
+        while let Some((dir, dir_local_path)) = stack.pop() {
+            async_std::fs::create_dir_all(&dir_local_path)
+                .await
+                .map_err(wnfs_error("export_dir"))?;
+            let names: Vec<String> = dir.get_entries().cloned().collect();
+            for name in names {
+                check_local_name(&name)?;
+                let child_local_path = dir_local_path.join(&name);
+                let node = dir
+                    .lookup_node(&name, true, &self.forest, &self.store)
+                    .await
+                    .map_err(wnfs_error("export_dir"))?
+                    .ok_or_else(|| WnfsUtilsError::NotFound(name.to_owned()))
+                    .map_err(wnfs_error("export_dir"))?;
+                match node {
+                    PrivateNode::Dir(child_dir) => stack.push((child_dir, child_local_path)),
+                    PrivateNode::File(file) => {
+                        let local_file = async_std::fs::File::create(&child_local_path)
+                            .await
+                            .map_err(wnfs_error("export_dir"))?;
+                        self.stream_file_content(&file, 0, local_file).await?;
+                        if let Some(modified) = file.get_metadata().get_modified() {
+                            set_modification_time(&child_local_path, modified.into())
+                                .map_err(wnfs_error("export_dir"))?;
+                        }
+                        files_exported += 1;
+                    }
+                }
+            }
+        }
+        Ok(files_exported)
+    }
+
+    pub async fn export_dir_async(
+        &mut self,
+        source_path_segments: &[String],
+        local_path: &String,
+    ) -> Result<usize, WnfsUtilsError> {
+        self.export_dir(source_path_segments, local_path).await
+    }
+
+    pub async fn import_dir_async(
+        &mut self,
+        local_path: &String,
+        target_path_segments: &[String],
+        progress: impl FnMut(&ImportProgress),
+    ) -> Result<Cid, WnfsUtilsError> {
+        self.import_dir(local_path, target_path_segments, progress)
+            .await
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Functions
+//--------------------------------------------------------------------------------------------------
+
+// Checks that the entry `name` can be used as a local file name, so that an export can't
+// escape its target directory with names like `..`, `/etc` or `a\b`.
+fn check_local_name(name: &str) -> Result<(), WnfsUtilsError> {
+    let mut components = Path::new(name).components();
+    match (components.next(), components.next()) {
+        (Some(Component::Normal(_)), None) if !name.contains(['/', '\\']) => Ok(()),
+        _ => Err(WnfsUtilsError::InvalidPath),
+    }
+}
+
+// Walks `local_path` depth-first in name order, listing every directory before its contents.
+// Symbolic links are skipped.
+fn scan_local_dir(
+    local_path: &Path,
+    target_path_segments: &[String],
+) -> Result<Vec<LocalEntry>, WnfsUtilsError> {
+    let mut entries = Vec::new();
+    let mut stack = vec![(local_path.to_path_buf(), target_path_segments.to_vec())];
+    while let Some((dir, dir_segments)) = stack.pop() {
+        if dir_segments != target_path_segments {
+            entries.push(LocalEntry::Dir {
+                path_segments: dir_segments.to_owned(),
+            });
+        }
+        let mut children = std::fs::read_dir(&dir)?.collect::<Result<Vec<_>, _>>()?;
+        children.sort_by_key(|child| child.file_name());
+        let mut subdirs = Vec::new();
+        for child in children {
+            let mut path_segments = dir_segments.to_owned();
+            path_segments.push(child.file_name().to_string_lossy().into_owned());
+            let file_type = child.file_type()?;
+            if file_type.is_dir() {
+                subdirs.push((child.path(), path_segments));
+            } else if file_type.is_file() {
+                let metadata = child.metadata()?;
+                let modification_time_seconds = metadata
+                    .modified()
+                    .map(|modified| DateTime::<Utc>::from(modified).timestamp())
+                    .unwrap_or_default();
+                entries.push(LocalEntry::File {
+                    local_path: child.path(),
+                    path_segments,
+                    size: metadata.len(),
+                    modification_time_seconds,
+                });
+            }
+        }
+        stack.extend(subdirs.into_iter().rev());
+    }
+    Ok(entries)
+}
+
+fn set_modification_time(local_path: &Path, modified: SystemTime) -> std::io::Result<()> {
+    std::fs::File::options()
+        .write(true)
+        .open(local_path)?
+        .set_modified(modified)
+}
+
+#[cfg(test)]
+mod localfs_tests;
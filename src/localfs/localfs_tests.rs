@@ -0,0 +1,157 @@
+use std::fs;
+use tempfile::tempdir;
+
+use crate::blockstore::FFIFriendlyBlockStore;
+use crate::error::WnfsUtilsError;
+use crate::memorystore::MemoryBlockStore;
+use crate::private_forest::PrivateDirectoryHelper;
+
+#[tokio::test]
+async fn test_import_dir() {
+    let local_dir = tempdir().unwrap();
+    fs::create_dir_all(local_dir.path().join("photos/2023")).unwrap();
+    fs::create_dir_all(local_dir.path().join("empty")).unwrap();
+    fs::write(local_dir.path().join("readme.txt"), b"readme").unwrap();
+    fs::write(local_dir.path().join("photos/a.jpg"), vec![1u8; 1024]).unwrap();
+    fs::write(local_dir.path().join("photos/2023/b.jpg"), vec![2u8; 2048]).unwrap();
+
+    let store = MemoryBlockStore::new();
+    let blockstore = &mut FFIFriendlyBlockStore::new(Box::new(store));
+    let (helper, _, _) = &mut PrivateDirectoryHelper::init(blockstore, vec![0; 32])
+        .await
+        .unwrap();
+
+    let mut reported = Vec::new();
+    let local_path = local_dir.path().to_string_lossy().into_owned();
+    let cid = helper
+        .import_dir(&local_path, &["root".into(), "backup".into()], |progress| {
+            reported.push(progress.to_owned())
+        })
+        .await
+        .unwrap();
+
+    let last = reported.last().unwrap();
+    assert_eq!(last.files_done, 3);
+    assert_eq!(last.files_total, 3);
+    assert_eq!(last.bytes_done, 6 + 1024 + 2048);
+
+    let reloaded = &mut PrivateDirectoryHelper::load_with_wnfs_key(blockstore, cid, vec![0; 32])
+        .await
+        .unwrap();
+    let ls_result = reloaded
+        .ls_files(&["root".into(), "backup".into()])
+        .await
+        .unwrap();
+    let names: Vec<String> = ls_result.iter().map(|(name, _)| name.clone()).collect();
+    assert_eq!(names, vec!["empty", "photos", "readme.txt"]);
+
+    let content = reloaded
+        .read_file(&[
+            "root".into(),
+            "backup".into(),
+            "photos".into(),
+            "2023".into(),
+            "b.jpg".into(),
+        ])
+        .await
+        .unwrap();
+    assert_eq!(content, vec![2u8; 2048]);
+}
+
+#[tokio::test]
+async fn test_export_dir() {
+    let store = MemoryBlockStore::new();
+    let blockstore = &mut FFIFriendlyBlockStore::new(Box::new(store));
+    let (helper, _, _) = &mut PrivateDirectoryHelper::init(blockstore, vec![0; 32])
+        .await
+        .unwrap();
+    helper
+        .write_file(
+            &["root".into(), "docs".into(), "a.txt".into()],
+            b"a".to_vec(),
+            1_600_000_000,
+        )
+        .await
+        .unwrap();
+    helper
+        .write_file(
+            &[
+                "root".into(),
+                "docs".into(),
+                "nested".into(),
+                "b.txt".into(),
+            ],
+            b"b".to_vec(),
+            0,
+        )
+        .await
+        .unwrap();
+    helper
+        .mkdir(&["root".into(), "docs".into(), "empty".into()])
+        .await
+        .unwrap();
+
+    let local_dir = tempdir().unwrap();
+    let local_path = local_dir
+        .path()
+        .join("export")
+        .to_string_lossy()
+        .into_owned();
+    let files_exported = helper
+        .export_dir(&["root".into(), "docs".into()], &local_path)
+        .await
+        .unwrap();
+
+    assert_eq!(files_exported, 2);
+    let export_path = local_dir.path().join("export");
+    assert_eq!(fs::read(export_path.join("a.txt")).unwrap(), b"a".to_vec());
+    assert_eq!(
+        fs::read(export_path.join("nested/b.txt")).unwrap(),
+        b"b".to_vec()
+    );
+    assert!(export_path.join("empty").is_dir());
+
+    let modified = fs::metadata(export_path.join("a.txt"))
+        .unwrap()
+        .modified()
+        .unwrap();
+    assert_eq!(
+        modified
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs(),
+        1_600_000_000
+    );
+}
+
+#[tokio::test]
+async fn test_export_dir_rejects_unsafe_names() {
+    let store = MemoryBlockStore::new();
+    let blockstore = &mut FFIFriendlyBlockStore::new(Box::new(store));
+    let (helper, _, _) = &mut PrivateDirectoryHelper::init(blockstore, vec![0; 32])
+        .await
+        .unwrap();
+    let local_dir = tempdir().unwrap();
+    for (index, name) in ["..", "a/b", "a\\b"].into_iter().enumerate() {
+        let source = ["root".to_string(), format!("docs_{}", index)];
+        helper
+            .write_file(
+                &[source.to_vec(), vec![name.into(), "escape.txt".into()]].concat(),
+                b"x".to_vec(),
+                0,
+            )
+            .await
+            .unwrap();
+
+        let local_path = local_dir
+            .path()
+            .join("export")
+            .join(index.to_string())
+            .to_string_lossy()
+            .into_owned();
+        let err = helper.export_dir(&source, &local_path).await.unwrap_err();
+        assert_eq!(err, WnfsUtilsError::InvalidPath);
+    }
+    assert!(!local_dir.path().join("export/escape.txt").exists());
+    assert!(!local_dir.path().join("export/1/a").exists());
+}
@@ -0,0 +1,124 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use libipld::Cid;
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{Arc, Mutex},
+};
+use wnfs::common::BlockStoreError;
+
+use crate::blockstore::FFIStore;
+
+/// In-memory block store. Clones share the same underlying map, so a store can be handed
+/// to several helpers (e.g. `init` then `reload`) and still see the same blocks.
+#[derive(Clone, Default)]
+pub struct MemoryBlockStore {
+    blocks: Arc<Mutex<Blocks>>,
+    capacity_bytes: Option<usize>,
+}
+
+// The stored blocks and the running sum of their sizes, so the capacity check doesn't have
+// to add up all blocks on every put.
+#[derive(Default)]
+struct Blocks {
+    map: HashMap<Vec<u8>, Vec<u8>>,
+    used_bytes: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MemoryBlockStoreError {
+    /// Storing the block would exceed the configured capacity.
+    StoreFull {
+        capacity_bytes: usize,
+        used_bytes: usize,
+        requested_bytes: usize,
+    },
+}
+
+//--------------------------------------------------------------------------------------------------
+// Implementations
+//--------------------------------------------------------------------------------------------------
+
+impl MemoryBlockStore {
+    /// Creates a new memory block store without a size limit.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a new memory block store that holds at most `capacity_bytes` of block data.
+    pub fn with_capacity_limit(capacity_bytes: usize) -> Self {
+        Self {
+            blocks: Default::default(),
+            capacity_bytes: Some(capacity_bytes),
+        }
+    }
+
+    /// Returns the number of bytes of block data currently stored.
+    pub fn used_bytes(&self) -> usize {
+        self.blocks.lock().unwrap().used_bytes
+    }
+
+    /// Returns the number of blocks currently stored.
+    pub fn len(&self) -> usize {
+        self.blocks.lock().unwrap().map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[async_trait(?Send)]
+impl<'a> FFIStore<'a> for MemoryBlockStore {
+    /// Retrieves an array of bytes from the block store with given CID.
+    async fn get_block(&self, cid: Vec<u8>) -> Result<Vec<u8>> {
+        let blocks = self.blocks.lock().unwrap();
+        match blocks.map.get(&cid) {
+            Some(bytes) => Ok(bytes.to_owned()),
+            None => Err(BlockStoreError::CIDNotFound(Cid::try_from(cid)?).into()),
+        }
+    }
+
+    /// Stores an array of bytes in the block store.
+    async fn put_block(&self, cid: Vec<u8>, bytes: Vec<u8>) -> Result<()> {
+        let mut blocks = self.blocks.lock().unwrap();
+        if blocks.map.contains_key(&cid) {
+            return Ok(());
+        }
+        if let Some(capacity_bytes) = self.capacity_bytes {
+            if blocks.used_bytes + bytes.len() > capacity_bytes {
+                return Err(MemoryBlockStoreError::StoreFull {
+                    capacity_bytes,
+                    used_bytes: blocks.used_bytes,
+                    requested_bytes: bytes.len(),
+                }
+                .into());
+            }
+        }
+        blocks.used_bytes += bytes.len();
+        blocks.map.insert(cid, bytes);
+        Ok(())
+    }
+}
+
+impl fmt::Display for MemoryBlockStoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MemoryBlockStoreError::StoreFull {
+                capacity_bytes,
+                used_bytes,
+                requested_bytes,
+            } => write!(
+                f,
+                "memory block store full: {} of {} bytes used, {} bytes requested",
+                used_bytes, capacity_bytes, requested_bytes
+            ),
+        }
+    }
+}
+
+impl std::error::Error for MemoryBlockStoreError {}
+
+#[cfg(test)]
+mod memorystore_tests;
@@ -0,0 +1,56 @@
+use libipld::{cbor::DagCborCodec, codec::Encode, IpldCodec};
+
+use wnfs::common::BlockStore;
+
+use crate::{
+    blockstore::FFIFriendlyBlockStore,
+    memorystore::{MemoryBlockStore, MemoryBlockStoreError},
+};
+
+#[tokio::test]
+async fn inserted_items_can_be_fetched() {
+    let store = MemoryBlockStore::new();
+    let blockstore = &mut FFIFriendlyBlockStore::new(Box::new(store.to_owned()));
+    let bytes = {
+        let mut tmp = vec![];
+        b"hello world"
+            .to_vec()
+            .encode(DagCborCodec, &mut tmp)
+            .unwrap();
+        tmp
+    };
+
+    let cid = &blockstore
+        .put_block(bytes, IpldCodec::DagCbor.into())
+        .await
+        .unwrap();
+
+    let loaded: Vec<u8> = blockstore.get_deserializable(cid).await.unwrap();
+    assert_eq!(loaded, b"hello world".to_vec());
+    assert_eq!(store.len(), 1);
+}
+
+#[tokio::test]
+async fn put_fails_when_store_is_full() {
+    let store = MemoryBlockStore::with_capacity_limit(16);
+    let blockstore = &mut FFIFriendlyBlockStore::new(Box::new(store.to_owned()));
+
+    blockstore
+        .put_block(vec![1u8; 10], IpldCodec::Raw.into())
+        .await
+        .unwrap();
+    let err = blockstore
+        .put_block(vec![2u8; 10], IpldCodec::Raw.into())
+        .await
+        .unwrap_err();
+
+    assert_eq!(
+        err.downcast_ref::<MemoryBlockStoreError>(),
+        Some(&MemoryBlockStoreError::StoreFull {
+            capacity_bytes: 16,
+            used_bytes: 10,
+            requested_bytes: 10,
+        })
+    );
+    assert_eq!(store.used_bytes(), 10);
+}
@@ -0,0 +1,449 @@
+//! Three-way merge of two forest cids of the same drive that diverged from a common
+//! ancestor, e.g. after two devices wrote while offline. Without a merge, whichever device
+//! stores its forest last silently discards the changes of the other.
+
+use libipld::{Cid, Ipld};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::rc::Rc;
+use wnfs::private::{PrivateFile, PrivateNode};
+
+use crate::diff::find_node;
+use crate::error::WnfsUtilsError;
+use crate::private_forest::{wnfs_error, PrivateDirectoryHelper};
+use crate::stat::custom_metadata;
+use crate::usage::Quota;
+
+/// How `PrivateDirectoryHelper::merge` resolves a path that both sides changed in different
+/// ways. With every policy but `Fail`, a removal loses against a change on the other side.
+/// Directories on both sides are merged, and so is their custom metadata, key by key. A key
+/// both sides changed to different values is a conflict as well.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictPolicy {
+    /// Keeps our version at the path and their version next to it, e.g. `a (conflict).txt`.
+    /// Between a file and a directory, the directory stays at the path. Between two values
+    /// of a directory's metadata key, ours is kept.
+    #[default]
+    KeepBoth,
+    /// Keeps the version with the later modification time. Ours wins ties.
+    LastWriterWins,
+    /// Fails with `MergeConflict` without changing anything.
+    Fail,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Side {
+    Ours,
+    Theirs,
+}
+
+// The outcome of a path changed by both sides.
+enum Resolution {
+    // Both sides agree, their changes below the path are merged.
+    Merged,
+    // Both sides have a directory at the path, which gets the merged custom `metadata`.
+    // `conflict` is set if both sides changed a key to different values.
+    MergedDir {
+        metadata: BTreeMap<String, Ipld>,
+        conflict: bool,
+    },
+    // `winner` replaces the whole subtree. If `keep_loser` is set, the losing file is kept
+    // as a conflict copy.
+    Conflict {
+        winner: Side,
+        keep_loser: bool,
+    },
+}
+
+//--------------------------------------------------------------------------------------------------
+// Implementations
+//--------------------------------------------------------------------------------------------------
+
+impl<'a> PrivateDirectoryHelper<'a> {
+    /// Merges the changes made in `ours_forest_cid` and `theirs_forest_cid` since
+    /// `base_forest_cid`, their common ancestor, and commits the result. The forests are
+    /// merged so that the history of both sides is kept, and the root directory is written
+    /// as a new revision after both of them. Afterwards this helper points at the result.
+    pub async fn merge(
+        &mut self,
+        base_forest_cid: Cid,
+        ours_forest_cid: Cid,
+        theirs_forest_cid: Cid,
+        policy: ConflictPolicy,
+    ) -> Result<Cid, WnfsUtilsError> {
+        self.check_writable()?;
+        let mut session = self.session();
+        let base = session
+            .load(base_forest_cid)
+            .await
+            .map_err(wnfs_error("merge"))?;
+        let ours = session
+            .load(ours_forest_cid)
+            .await
+            .map_err(wnfs_error("merge"))?;
+        let theirs = session
+            .load(theirs_forest_cid)
+            .await
+            .map_err(wnfs_error("merge"))?;
+
+        // Paths changed by (ours, theirs). Parents sort before their contents.
+        let mut changed: BTreeMap<Vec<String>, (bool, bool)> = BTreeMap::new();
+        for change in self.diff(base_forest_cid, ours_forest_cid, &[]).await? {
+            changed.entry(change.path_segments).or_default().0 = true;
+        }
+        for change in self.diff(base_forest_cid, theirs_forest_cid, &[]).await? {
+            changed.entry(change.path_segments).or_default().1 = true;
+        }
+
+        // Every path is resolved before anything is written, so that a failed merge leaves
+        // the drive untouched.
+        let mut plan: Vec<(Vec<String>, Side, Option<Side>)> = Vec::new();
+        let mut owners: Vec<(Vec<String>, Side)> = Vec::new();
+        let mut dir_metadata: Vec<(Vec<String>, BTreeMap<String, Ipld>)> = Vec::new();
+        let mut conflicts = Vec::new();
+        for (path, (ours_changed, theirs_changed)) in changed {
+            let owner = owners
+                .iter()
+                .find(|(prefix, _)| path.starts_with(prefix))
+                .map(|(_, side)| *side);
+            if let Some(side) = owner {
+                plan.push((path, side, None));
+                continue;
+            }
+            if !theirs_changed || !ours_changed {
+                let side = if theirs_changed {
+                    Side::Theirs
+                } else {
+                    Side::Ours
+                };
+                plan.push((path, side, None));
+                continue;
+            }
+            let base_node = find_node(&base, &path).await?;
+            let ours_node = find_node(&ours, &path).await?;
+            let theirs_node = find_node(&theirs, &path).await?;
+            match resolve(base_node, &ours, ours_node, &theirs, theirs_node, policy).await? {
+                Resolution::Merged => plan.push((path, Side::Ours, None)),
+                Resolution::MergedDir { conflict: true, .. } if policy == ConflictPolicy::Fail => {
+                    conflicts.push(path.join("/"))
+                }
+                Resolution::MergedDir { metadata, .. } => {
+                    dir_metadata.push((path.to_owned(), metadata));
+                    plan.push((path, Side::Ours, None));
+                }
+                Resolution::Conflict { .. } if policy == ConflictPolicy::Fail => {
+                    conflicts.push(path.join("/"))
+                }
+                Resolution::Conflict { winner, keep_loser } => {
+                    let loser = match winner {
+                        Side::Ours => Side::Theirs,
+                        Side::Theirs => Side::Ours,
+                    };
+                    owners.push((path.to_owned(), winner));
+                    plan.push((path, winner, keep_loser.then_some(loser)));
+                }
+            }
+        }
+        if !conflicts.is_empty() {
+            return Err(WnfsUtilsError::MergeConflict(conflicts));
+        }
+
+        let mut merged = session
+            .load(ours_forest_cid)
+            .await
+            .map_err(wnfs_error("merge"))?;
+        merged.forest = Rc::new(
+            ours.forest
+                .merge(&theirs.forest, &merged.store)
+                .await
+                .map_err(wnfs_error("merge"))?,
+        );
+        // Continue from the latest revision of either side, so that the merged revision
+        // comes after both of them.
+        merged.root_dir = merged
+            .root_dir
+            .as_node()
+            .search_latest(&merged.forest, &merged.store)
+            .await
+            .map_err(wnfs_error("merge"))?
+            .as_dir()
+            .map_err(wnfs_error("merge"))?;
+        // The drive state continues from its latest revision as well, which is the state of
+        // one of the sides, and gets the share registry and uploads of both merged into it.
+        merged.state_dir = Self::load_state_dir(
+            &merged.forest,
+            &merged.store,
+            &merged.wnfs_key,
+            &merged.exchange_keypair,
+        )
+        .await?;
+        merged
+            .apply_merge_share_registry(&base, &ours, &theirs)
+            .await?;
+        merged.apply_merge_uploads(&base, &ours, &theirs).await?;
+        // The merged changes are checked against our quota, counted from the revision the
+        // merge continues from, and our quota replaces the one of the other side.
+        merged.quota = match self.quota {
+            Some(quota) => Some(Quota {
+                quota_bytes: quota.quota_bytes,
+                used_bytes: merged.du(&[]).await?.logical_bytes,
+            }),
+            None => None,
+        };
+        if merged.quota.is_none() {
+            merged.apply_rm_quota().await?;
+        }
+        for (path, side, loser) in plan {
+            let source = match side {
+                Side::Ours => &ours,
+                Side::Theirs => &theirs,
+            };
+            merged.apply_node_from(&path, source).await?;
+            if let Some(loser) = loser {
+                let source = match loser {
+                    Side::Ours => &ours,
+                    Side::Theirs => &theirs,
+                };
+                if let Some(PrivateNode::File(file)) = find_node(source, &path).await? {
+                    let copy_path = merged.conflict_copy_path(&path).await?;
+                    merged
+                        .apply_write_file_from(&copy_path, source, &file)
+                        .await?;
+                }
+            }
+        }
+        for (path, metadata) in dir_metadata {
+            merged.apply_replace_metadata(&path, metadata).await?;
+        }
+        let forest_cid = merged.store_root_dir("merge").await?;
+        *self = merged;
+        Ok(forest_cid)
+    }
+
+    // Makes the node at `path_segments` look like it does in `source`, without touching the
+    // contents of a directory.
+    async fn apply_node_from(
+        &mut self,
+        path_segments: &[String],
+        source: &PrivateDirectoryHelper<'_>,
+    ) -> Result<(), WnfsUtilsError> {
+        let current_node = find_node(self, path_segments).await?;
+        match find_node(source, path_segments).await? {
+            None => {
+                if current_node.is_some() {
+                    self.apply_rm(path_segments).await?;
+                }
+            }
+            Some(PrivateNode::File(file)) => {
+                if let Some(PrivateNode::Dir(_)) = current_node {
+                    self.apply_rm(path_segments).await?;
+                }
+                self.apply_write_file_from(path_segments, source, &file)
+                    .await?;
+            }
+            Some(PrivateNode::Dir(dir)) => {
+                match current_node {
+                    Some(PrivateNode::Dir(_)) => {}
+                    Some(PrivateNode::File(_)) => {
+                        self.apply_rm(path_segments).await?;
+                        self.apply_mkdir(path_segments).await?;
+                    }
+                    None => self.apply_mkdir(path_segments).await?,
+                }
+                self.apply_replace_metadata(path_segments, custom_metadata(dir.get_metadata()))
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+
+    // Finds a free name next to `path_segments` for a conflict copy, e.g. `a (conflict).txt`
+    // or `a (conflict 2).txt`.
+    async fn conflict_copy_path(
+        &self,
+        path_segments: &[String],
+    ) -> Result<Vec<String>, WnfsUtilsError> {
+        let Some((name, parent)) = path_segments.split_last() else {
+            return Err(WnfsUtilsError::InvalidPath);
+        };
+        let (stem, extension) = match name.rsplit_once('.') {
+            Some((stem, extension)) if !stem.is_empty() => (stem, format!(".{}", extension)),
+            _ => (name.as_str(), String::new()),
+        };
+        let mut number = 1;
+        loop {
+            let suffix = match number {
+                1 => "conflict".to_owned(),
+                _ => format!("conflict {}", number),
+            };
+            let copy_path = [
+                parent.to_vec(),
+                vec![format!("{} ({}){}", stem, suffix, extension)],
+            ]
+            .concat();
+            if find_node(self, &copy_path).await?.is_none() {
+                return Ok(copy_path);
+            }
+            number += 1;
+        }
+    }
+
+    pub async fn merge_async(
+        &mut self,
+        base_forest_cid: Cid,
+        ours_forest_cid: Cid,
+        theirs_forest_cid: Cid,
+        policy: ConflictPolicy,
+    ) -> Result<Cid, WnfsUtilsError> {
+        self.merge(base_forest_cid, ours_forest_cid, theirs_forest_cid, policy)
+            .await
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Functions
+//--------------------------------------------------------------------------------------------------
+
+// Decides which side wins a path that both sides changed since `base_node`. `policy` only
+// matters for real conflicts, `Fail` is handled by the caller.
+async fn resolve(
+    base_node: Option<PrivateNode>,
+    ours: &PrivateDirectoryHelper<'_>,
+    ours_node: Option<PrivateNode>,
+    theirs: &PrivateDirectoryHelper<'_>,
+    theirs_node: Option<PrivateNode>,
+    policy: ConflictPolicy,
+) -> Result<Resolution, WnfsUtilsError> {
+    let (ours_node, theirs_node) = match (ours_node, theirs_node) {
+        (None, None) => return Ok(Resolution::Merged),
+        (Some(_), None) => {
+            return Ok(Resolution::Conflict {
+                winner: Side::Ours,
+                keep_loser: false,
+            })
+        }
+        (None, Some(_)) => {
+            return Ok(Resolution::Conflict {
+                winner: Side::Theirs,
+                keep_loser: false,
+            })
+        }
+        (Some(ours_node), Some(theirs_node)) => (ours_node, theirs_node),
+    };
+    match (&ours_node, &theirs_node) {
+        (PrivateNode::Dir(ours_dir), PrivateNode::Dir(theirs_dir)) => {
+            let base_metadata = match &base_node {
+                Some(PrivateNode::Dir(base_dir)) => custom_metadata(base_dir.get_metadata()),
+                _ => BTreeMap::new(),
+            };
+            let preferred = match policy {
+                ConflictPolicy::LastWriterWins if modified(&theirs_node) > modified(&ours_node) => {
+                    Side::Theirs
+                }
+                _ => Side::Ours,
+            };
+            let (metadata, conflict) = merge_metadata(
+                &base_metadata,
+                &custom_metadata(ours_dir.get_metadata()),
+                &custom_metadata(theirs_dir.get_metadata()),
+                preferred,
+            );
+            return Ok(Resolution::MergedDir { metadata, conflict });
+        }
+        (PrivateNode::File(ours_file), PrivateNode::File(theirs_file)) => {
+            if same_content(ours, ours_file, theirs, theirs_file).await? {
+                return Ok(Resolution::Merged);
+            }
+        }
+        _ => {}
+    }
+    Ok(match policy {
+        ConflictPolicy::LastWriterWins => Resolution::Conflict {
+            winner: if modified(&theirs_node) > modified(&ours_node) {
+                Side::Theirs
+            } else {
+                Side::Ours
+            },
+            keep_loser: false,
+        },
+        ConflictPolicy::KeepBoth | ConflictPolicy::Fail => Resolution::Conflict {
+            winner: if matches!(theirs_node, PrivateNode::Dir(_)) {
+                Side::Theirs
+            } else {
+                Side::Ours
+            },
+            keep_loser: true,
+        },
+    })
+}
+
+// Merges the custom metadata of a directory key by key. A key both sides changed to
+// different values is a conflict and gets the value of `preferred`.
+fn merge_metadata(
+    base: &BTreeMap<String, Ipld>,
+    ours: &BTreeMap<String, Ipld>,
+    theirs: &BTreeMap<String, Ipld>,
+    preferred: Side,
+) -> (BTreeMap<String, Ipld>, bool) {
+    let keys: BTreeSet<&String> = base
+        .keys()
+        .chain(ours.keys())
+        .chain(theirs.keys())
+        .collect();
+    let mut metadata = BTreeMap::new();
+    let mut conflict = false;
+    for key in keys {
+        let (base_value, ours_value, theirs_value) =
+            (base.get(key), ours.get(key), theirs.get(key));
+        let value = if ours_value == theirs_value || theirs_value == base_value {
+            ours_value
+        } else if ours_value == base_value {
+            theirs_value
+        } else {
+            conflict = true;
+            match preferred {
+                Side::Ours => ours_value,
+                Side::Theirs => theirs_value,
+            }
+        };
+        if let Some(value) = value {
+            metadata.insert(key.to_owned(), value.to_owned());
+        }
+    }
+    (metadata, conflict)
+}
+
+async fn same_content(
+    ours: &PrivateDirectoryHelper<'_>,
+    ours_file: &PrivateFile,
+    theirs: &PrivateDirectoryHelper<'_>,
+    theirs_file: &PrivateFile,
+) -> Result<bool, WnfsUtilsError> {
+    if ours_file == theirs_file {
+        return Ok(true);
+    }
+    if ours.file_size(ours_file).await? != theirs.file_size(theirs_file).await? {
+        return Ok(false);
+    }
+    let ours_content = ours_file
+        .get_content(&ours.forest, &ours.store)
+        .await
+        .map_err(wnfs_error("merge"))?;
+    let theirs_content = theirs_file
+        .get_content(&theirs.forest, &theirs.store)
+        .await
+        .map_err(wnfs_error("merge"))?;
+    Ok(ours_content == theirs_content)
+}
+
+fn modified(node: &PrivateNode) -> Option<i64> {
+    let metadata = match node {
+        PrivateNode::File(file) => file.get_metadata(),
+        PrivateNode::Dir(dir) => dir.get_metadata(),
+    };
+    metadata.get_modified().map(|modified| modified.timestamp())
+}
+
+#[cfg(test)]
+mod merge_tests;
@@ -0,0 +1,248 @@
+use libipld::{Cid, Ipld};
+
+use crate::blockstore::FFIFriendlyBlockStore;
+use crate::error::WnfsUtilsError;
+use crate::memorystore::MemoryBlockStore;
+use crate::merge::ConflictPolicy;
+use crate::private_forest::PrivateDirectoryHelper;
+
+// Writes a common base, then diverges into two forests as two devices would. Both sides
+// change `a.txt`, ours adds `ours.txt`, theirs changes `b.txt` and removes `c.txt`.
+async fn diverge<'a>(
+    blockstore: &mut FFIFriendlyBlockStore<'a>,
+) -> (PrivateDirectoryHelper<'a>, Cid, Cid, Cid) {
+    let (mut helper, _, _) = PrivateDirectoryHelper::init(blockstore, vec![0; 32])
+        .await
+        .unwrap();
+    for name in ["a.txt", "b.txt", "c.txt"] {
+        helper
+            .write_file(&[name.into()], b"base".to_vec(), 1_700_000_000)
+            .await
+            .unwrap();
+    }
+    let base_cid = helper.mkdir(&["docs".into()]).await.unwrap();
+
+    let mut theirs = PrivateDirectoryHelper::load_with_wnfs_key(blockstore, base_cid, vec![0; 32])
+        .await
+        .unwrap();
+    theirs
+        .write_file(&["a.txt".into()], b"theirs".to_vec(), 1_700_000_200)
+        .await
+        .unwrap();
+    theirs
+        .write_file(&["b.txt".into()], b"theirs".to_vec(), 1_700_000_200)
+        .await
+        .unwrap();
+    let theirs_cid = theirs.rm(&["c.txt".into()]).await.unwrap();
+
+    helper
+        .write_file(&["a.txt".into()], b"ours".to_vec(), 1_700_000_100)
+        .await
+        .unwrap();
+    let ours_cid = helper
+        .write_file(&["docs".into(), "ours.txt".into()], b"ours".to_vec(), 0)
+        .await
+        .unwrap();
+    (helper, base_cid, ours_cid, theirs_cid)
+}
+
+#[tokio::test]
+async fn merge_keeps_both_versions_of_conflicts() {
+    let store = MemoryBlockStore::new();
+    let blockstore = &mut FFIFriendlyBlockStore::new(Box::new(store));
+    let (mut helper, base_cid, ours_cid, theirs_cid) = diverge(blockstore).await;
+
+    let cid = helper
+        .merge(base_cid, ours_cid, theirs_cid, ConflictPolicy::KeepBoth)
+        .await
+        .unwrap();
+    let helper = &mut PrivateDirectoryHelper::load_with_wnfs_key(blockstore, cid, vec![0; 32])
+        .await
+        .unwrap();
+    assert_eq!(helper.read_file(&["a.txt".into()]).await.unwrap(), b"ours");
+    assert_eq!(
+        helper
+            .read_file(&["a (conflict).txt".into()])
+            .await
+            .unwrap(),
+        b"theirs"
+    );
+    assert_eq!(
+        helper.read_file(&["b.txt".into()]).await.unwrap(),
+        b"theirs"
+    );
+    assert!(!helper.exists(&["c.txt".into()]).await.unwrap());
+    assert_eq!(
+        helper
+            .read_file(&["docs".into(), "ours.txt".into()])
+            .await
+            .unwrap(),
+        b"ours"
+    );
+}
+
+#[tokio::test]
+async fn merge_last_writer_wins() {
+    let store = MemoryBlockStore::new();
+    let blockstore = &mut FFIFriendlyBlockStore::new(Box::new(store));
+    let (mut helper, base_cid, ours_cid, theirs_cid) = diverge(blockstore).await;
+
+    helper
+        .merge(
+            base_cid,
+            ours_cid,
+            theirs_cid,
+            ConflictPolicy::LastWriterWins,
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        helper.read_file(&["a.txt".into()]).await.unwrap(),
+        b"theirs"
+    );
+    assert!(!helper.exists(&["a (conflict).txt".into()]).await.unwrap());
+    assert!(helper
+        .exists(&["docs".into(), "ours.txt".into()])
+        .await
+        .unwrap());
+}
+
+#[tokio::test]
+async fn merge_fails_on_conflict_without_changes() {
+    let store = MemoryBlockStore::new();
+    let blockstore = &mut FFIFriendlyBlockStore::new(Box::new(store));
+    let (mut helper, base_cid, ours_cid, theirs_cid) = diverge(blockstore).await;
+
+    let err = helper
+        .merge(base_cid, ours_cid, theirs_cid, ConflictPolicy::Fail)
+        .await
+        .unwrap_err();
+    assert_eq!(err, WnfsUtilsError::MergeConflict(vec!["a.txt".into()]));
+    assert_eq!(helper.read_file(&["a.txt".into()]).await.unwrap(), b"ours");
+    assert_eq!(helper.read_file(&["b.txt".into()]).await.unwrap(), b"base");
+}
+
+#[tokio::test]
+async fn merge_merges_directory_metadata() {
+    let store = MemoryBlockStore::new();
+    let blockstore = &mut FFIFriendlyBlockStore::new(Box::new(store));
+    let (mut helper, _, _) = PrivateDirectoryHelper::init(blockstore, vec![0; 32])
+        .await
+        .unwrap();
+    helper.mkdir(&["docs".into()]).await.unwrap();
+    let base_cid = helper
+        .set_metadata(&["docs".into()], "color", Ipld::String("grey".into()))
+        .await
+        .unwrap();
+
+    let mut theirs = PrivateDirectoryHelper::load_with_wnfs_key(blockstore, base_cid, vec![0; 32])
+        .await
+        .unwrap();
+    theirs
+        .set_metadata(&["docs".into()], "pinned", Ipld::Bool(true))
+        .await
+        .unwrap();
+    let theirs_cid = theirs
+        .set_metadata(&["docs".into()], "color", Ipld::String("blue".into()))
+        .await
+        .unwrap();
+
+    // Keys changed by one side only are merged.
+    let ours_cid = helper
+        .set_metadata(&["docs".into()], "owner", Ipld::String("me".into()))
+        .await
+        .unwrap();
+    let mut merged = PrivateDirectoryHelper::load_with_wnfs_key(blockstore, ours_cid, vec![0; 32])
+        .await
+        .unwrap();
+    merged
+        .merge(base_cid, ours_cid, theirs_cid, ConflictPolicy::Fail)
+        .await
+        .unwrap();
+    let metadata = merged.get_metadata(&["docs".into()]).await.unwrap();
+    assert_eq!(metadata.get("color"), Some(&Ipld::String("blue".into())));
+    assert_eq!(metadata.get("pinned"), Some(&Ipld::Bool(true)));
+    assert_eq!(metadata.get("owner"), Some(&Ipld::String("me".into())));
+
+    // A key both sides changed differently is a conflict.
+    let ours_cid = helper
+        .set_metadata(&["docs".into()], "color", Ipld::String("red".into()))
+        .await
+        .unwrap();
+    let err = helper
+        .merge(base_cid, ours_cid, theirs_cid, ConflictPolicy::Fail)
+        .await
+        .unwrap_err();
+    assert_eq!(err, WnfsUtilsError::MergeConflict(vec!["docs".into()]));
+
+    helper
+        .merge(base_cid, ours_cid, theirs_cid, ConflictPolicy::KeepBoth)
+        .await
+        .unwrap();
+    let metadata = helper.get_metadata(&["docs".into()]).await.unwrap();
+    assert_eq!(metadata.get("color"), Some(&Ipld::String("red".into())));
+    assert_eq!(metadata.get("pinned"), Some(&Ipld::Bool(true)));
+    assert_eq!(metadata.get("owner"), Some(&Ipld::String("me".into())));
+}
+
+#[tokio::test]
+async fn merge_merges_drive_state_of_both_sides() {
+    let store = MemoryBlockStore::new();
+    let blockstore = &mut FFIFriendlyBlockStore::new(Box::new(store));
+    let (recipient, _, _) = PrivateDirectoryHelper::init(blockstore, vec![1; 32])
+        .await
+        .unwrap();
+    let recipient_key = recipient.exchange_public_key().unwrap();
+    let recipient_did = recipient.did().unwrap();
+
+    let (mut helper, _, _) = PrivateDirectoryHelper::init(blockstore, vec![0; 32])
+        .await
+        .unwrap();
+    helper.mkdir(&["photos".into()]).await.unwrap();
+    let (aborted_id, base_cid) = helper.begin_upload(&["a.bin".into()], 0).await.unwrap();
+
+    // Theirs shares a directory and adds a chunk to the upload ours aborts.
+    let mut theirs = PrivateDirectoryHelper::load_with_wnfs_key(blockstore, base_cid, vec![0; 32])
+        .await
+        .unwrap();
+    theirs
+        .share_path(&["photos".into()], &recipient_key, &recipient_did, false)
+        .await
+        .unwrap();
+    theirs
+        .upload_chunk(&aborted_id, 0, b"chunk".to_vec())
+        .await
+        .unwrap();
+    let (theirs_id, theirs_cid) = theirs.begin_upload(&["b.bin".into()], 0).await.unwrap();
+
+    helper.abort_upload(&aborted_id).await.unwrap();
+    let (ours_id, ours_cid) = helper.begin_upload(&["c.bin".into()], 0).await.unwrap();
+
+    for (ours_cid, theirs_cid) in [(ours_cid, theirs_cid), (theirs_cid, ours_cid)] {
+        let mut merged =
+            PrivateDirectoryHelper::load_with_wnfs_key(blockstore, ours_cid, vec![0; 32])
+                .await
+                .unwrap();
+        let cid = merged
+            .merge(base_cid, ours_cid, theirs_cid, ConflictPolicy::Fail)
+            .await
+            .unwrap();
+        let merged = &mut PrivateDirectoryHelper::load_with_wnfs_key(blockstore, cid, vec![0; 32])
+            .await
+            .unwrap();
+        let shares = merged.list_shares().await.unwrap();
+        assert_eq!(shares.len(), 1);
+        assert_eq!(shares[0].path_segments, vec!["photos".to_string()]);
+        let mut upload_ids: Vec<String> = merged
+            .list_uploads()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|session| session.upload_id)
+            .collect();
+        upload_ids.sort();
+        let mut expected = vec![ours_id.to_owned(), theirs_id.to_owned()];
+        expected.sort();
+        assert_eq!(upload_ids, expected);
+    }
+}
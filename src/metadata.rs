@@ -0,0 +1,159 @@
+//! Custom user metadata, e.g. a MIME type, tags or the cid of a thumbnail, stored next to the
+//! times wnfs maintains in the metadata of files and directories.
+
+use chrono::Utc;
+use libipld::{Cid, Ipld};
+use std::collections::BTreeMap;
+use wnfs::{common::Metadata, private::PrivateNode};
+
+use crate::error::WnfsUtilsError;
+use crate::private_forest::{wnfs_error, PrivateDirectoryHelper};
+use crate::stat::{custom_metadata, WNFS_METADATA_KEYS};
+
+//--------------------------------------------------------------------------------------------------
+// Implementations
+//--------------------------------------------------------------------------------------------------
+
+impl<'a> PrivateDirectoryHelper<'a> {
+    /// Sets the custom metadata entry `key` of the file or directory at `path_segments`.
+    pub async fn set_metadata(
+        &mut self,
+        path_segments: &[String],
+        key: &str,
+        value: Ipld,
+    ) -> Result<Cid, WnfsUtilsError> {
+        self.apply_set_metadata(path_segments, key, Some(value))
+            .await?;
+        self.store_root_dir("set_metadata").await
+    }
+
+    /// Returns the custom metadata entries of the file or directory at `path_segments`.
+    pub async fn get_metadata(
+        &mut self,
+        path_segments: &[String],
+    ) -> Result<BTreeMap<String, Ipld>, WnfsUtilsError> {
+        let node = self
+            .get_node(path_segments)
+            .await
+            .map_err(wnfs_error("get_metadata"))?;
+        Ok(match node {
+            PrivateNode::File(file) => custom_metadata(file.get_metadata()),
+            PrivateNode::Dir(dir) => custom_metadata(dir.get_metadata()),
+        })
+    }
+
+    /// Removes the custom metadata entry `key` of the file or directory at `path_segments`.
+    pub async fn remove_metadata(
+        &mut self,
+        path_segments: &[String],
+        key: &str,
+    ) -> Result<Cid, WnfsUtilsError> {
+        self.apply_set_metadata(path_segments, key, None).await?;
+        self.store_root_dir("remove_metadata").await
+    }
+
+    // Sets the metadata entry `key`, or removes it if `value` is `None`.
+    pub(crate) async fn apply_set_metadata(
+        &mut self,
+        path_segments: &[String],
+        key: &str,
+        value: Option<Ipld>,
+    ) -> Result<(), WnfsUtilsError> {
+        self.check_writable()?;
+        if WNFS_METADATA_KEYS.contains(&key) {
+            return Err(WnfsUtilsError::ReservedMetadataKey(key.to_owned()));
+        }
+        let metadata = self
+            .metadata_mut(path_segments)
+            .await
+            .map_err(wnfs_error("set_metadata"))?;
+        match value {
+            Some(value) => metadata.0.insert(key.to_owned(), value),
+            None => metadata.0.remove(key),
+        };
+        Ok(())
+    }
+
+    // Replaces all custom metadata entries of the node at `path_segments` with `metadata`,
+    // leaving entries that are already equal untouched.
+    pub(crate) async fn apply_replace_metadata(
+        &mut self,
+        path_segments: &[String],
+        metadata: BTreeMap<String, Ipld>,
+    ) -> Result<(), WnfsUtilsError> {
+        let current_metadata = self.get_metadata(path_segments).await?;
+        for key in current_metadata.keys() {
+            if !metadata.contains_key(key) {
+                self.apply_set_metadata(path_segments, key, None).await?;
+            }
+        }
+        for (key, value) in metadata {
+            if current_metadata.get(&key) != Some(&value) {
+                self.apply_set_metadata(path_segments, &key, Some(value))
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+
+    // Opens the next revision of the existing node at `path_segments` for changing its metadata.
+    async fn metadata_mut(
+        &mut self,
+        path_segments: &[String],
+    ) -> Result<&mut Metadata, WnfsUtilsError> {
+        let time = Utc::now();
+        match self.get_node(path_segments).await? {
+            PrivateNode::File(_) => Ok(self
+                .root_dir
+                .open_file_mut(
+                    path_segments,
+                    true,
+                    time,
+                    &mut self.forest,
+                    &mut self.store,
+                    &mut self.rng,
+                )
+                .await?
+                .get_metadata_mut()),
+            PrivateNode::Dir(_) => Ok(self
+                .root_dir
+                .get_or_create_leaf_dir_mut(
+                    path_segments,
+                    time,
+                    true,
+                    &mut self.forest,
+                    &self.store,
+                    &mut self.rng,
+                )
+                .await?
+                .get_metadata_mut()),
+        }
+    }
+
+    pub async fn set_metadata_async(
+        &mut self,
+        path_segments: &[String],
+        key: &str,
+        value: Ipld,
+    ) -> Result<Cid, WnfsUtilsError> {
+        self.set_metadata(path_segments, key, value).await
+    }
+
+    pub async fn get_metadata_async(
+        &mut self,
+        path_segments: &[String],
+    ) -> Result<BTreeMap<String, Ipld>, WnfsUtilsError> {
+        self.get_metadata(path_segments).await
+    }
+
+    pub async fn remove_metadata_async(
+        &mut self,
+        path_segments: &[String],
+        key: &str,
+    ) -> Result<Cid, WnfsUtilsError> {
+        self.remove_metadata(path_segments, key).await
+    }
+}
+
+#[cfg(test)]
+mod metadata_tests;
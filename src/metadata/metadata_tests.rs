@@ -0,0 +1,86 @@
+use libipld::Ipld;
+
+use crate::blockstore::FFIFriendlyBlockStore;
+use crate::error::WnfsUtilsError;
+use crate::memorystore::MemoryBlockStore;
+use crate::private_forest::PrivateDirectoryHelper;
+
+#[tokio::test]
+async fn metadata_is_persisted() {
+    let store = MemoryBlockStore::new();
+    let blockstore = &mut FFIFriendlyBlockStore::new(Box::new(store));
+    let (helper, _, _) = &mut PrivateDirectoryHelper::init(blockstore, vec![0; 32])
+        .await
+        .unwrap();
+    let file = ["root".to_string(), "photo.jpg".to_string()];
+    let dir = ["root".to_string()];
+    helper.write_file(&file, b"jpeg".to_vec(), 0).await.unwrap();
+
+    helper
+        .set_metadata(&file, "mime_type", Ipld::String("image/jpeg".into()))
+        .await
+        .unwrap();
+    helper
+        .set_metadata(
+            &file,
+            "tags",
+            Ipld::List(vec![Ipld::String("beach".into())]),
+        )
+        .await
+        .unwrap();
+    let cid = helper
+        .set_metadata(&dir, "color", Ipld::String("blue".into()))
+        .await
+        .unwrap();
+
+    let reloaded = &mut PrivateDirectoryHelper::load_with_wnfs_key(blockstore, cid, vec![0; 32])
+        .await
+        .unwrap();
+    let metadata = reloaded.get_metadata(&file).await.unwrap();
+    assert_eq!(metadata.len(), 2);
+    assert_eq!(
+        metadata.get("mime_type"),
+        Some(&Ipld::String("image/jpeg".into()))
+    );
+    let metadata = reloaded.get_metadata(&dir).await.unwrap();
+    assert_eq!(metadata.get("color"), Some(&Ipld::String("blue".into())));
+    assert_eq!(reloaded.read_file(&file).await.unwrap(), b"jpeg".to_vec());
+
+    reloaded.remove_metadata(&file, "tags").await.unwrap();
+    let metadata = reloaded.get_metadata(&file).await.unwrap();
+    assert_eq!(metadata.keys().collect::<Vec<_>>(), vec!["mime_type"]);
+}
+
+#[tokio::test]
+async fn metadata_errors() {
+    let store = MemoryBlockStore::new();
+    let blockstore = &mut FFIFriendlyBlockStore::new(Box::new(store));
+    let (helper, _, _) = &mut PrivateDirectoryHelper::init(blockstore, vec![0; 32])
+        .await
+        .unwrap();
+    helper
+        .write_file(&["root".into(), "a.txt".into()], b"a".to_vec(), 0)
+        .await
+        .unwrap();
+
+    let err = helper
+        .set_metadata(
+            &["root".into(), "a.txt".into()],
+            "modified",
+            Ipld::Integer(0),
+        )
+        .await
+        .unwrap_err();
+    assert_eq!(err, WnfsUtilsError::ReservedMetadataKey("modified".into()));
+    assert_eq!(err.code(), 13);
+
+    let err = helper
+        .set_metadata(&["root".into(), "b.txt".into()], "k", Ipld::Null)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, WnfsUtilsError::NotFound(_)));
+    assert!(!helper
+        .exists(&["root".into(), "b.txt".into()])
+        .await
+        .unwrap());
+}
@@ -3,12 +3,16 @@
 
 use async_trait::async_trait;
 use chrono::prelude::*;
+use futures::{
+    io, pin_mut, stream, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, Stream, StreamExt,
+    TryStreamExt,
+};
 use libipld::Cid;
 use rand::{rngs::ThreadRng, thread_rng};
 use rand_chacha::ChaCha12Rng;
 use rand_core::SeedableRng;
 use rsa::{traits::PublicKeyParts, BigUint, Oaep, RsaPrivateKey, RsaPublicKey};
-use std::{rc::Rc, sync::Mutex};
+use std::{cell::Cell, rc::Rc};
 
 use wnfs::{
     common::{BlockStore, Metadata, CODEC_RAW},
@@ -16,7 +20,8 @@ use wnfs::{
     private::{
         forest::{hamt::HamtForest, traits::PrivateForest},
         share::{recipient, sharer},
-        AccessKey, ExchangeKey, PrivateDirectory, PrivateKey, PUBLIC_KEY_EXPONENT,
+        AccessKey, ExchangeKey, PrivateDirectory, PrivateFile, PrivateKey, PrivateNode,
+        MAX_BLOCK_CONTENT_SIZE, PUBLIC_KEY_EXPONENT,
     },
     public::{PublicDirectory, PublicLink, PublicNode},
 };
@@ -26,28 +31,156 @@ use log::trace;
 use sha3::Sha3_256;
 
 use crate::blockstore::FFIFriendlyBlockStore;
+use crate::error::WnfsUtilsError;
+use crate::share::{ShareCounter, ShareOrigin};
+use crate::usage::Quota;
+
+/// Length in bytes of the wnfs key, which seeds the RSA exchange keypair of the drive.
+pub const WNFS_KEY_LENGTH: usize = 32;
 
+/// Block store and wnfs key of a single drive. Each session is independent, so several
+/// drives can be open in the same process at the same time.
 #[derive(Clone)]
-struct State {
-    initialized: bool,
+pub struct WnfsSession<'a> {
+    pub store: FFIFriendlyBlockStore<'a>,
     wnfs_key: Vec<u8>,
+    // For sessions of share handles, the share forest cids are opened through.
+    share: Option<ShareOrigin>,
+    // Exchange keypair of the wnfs key, derived on first use and shared by every helper the
+    // session opens.
+    exchange_keypair: Option<Rc<SeededExchangeKey>>,
 }
-impl State {
-    fn update(&mut self, initialized: bool, wnfs_key: Vec<u8>) {
-        self.initialized = initialized;
-        self.wnfs_key = wnfs_key;
+
+impl<'a> WnfsSession<'a> {
+    pub fn new(store: FFIFriendlyBlockStore<'a>, wnfs_key: Vec<u8>) -> Self {
+        Self {
+            store,
+            wnfs_key,
+            share: None,
+            exchange_keypair: None,
+        }
+    }
+
+    fn exchange_keypair(&mut self) -> Result<Rc<SeededExchangeKey>, WnfsUtilsError> {
+        if let Some(exchange_keypair) = &self.exchange_keypair {
+            return Ok(Rc::clone(exchange_keypair));
+        }
+        let exchange_keypair = PrivateDirectoryHelper::derive_exchange_keypair(&self.wnfs_key)?;
+        self.exchange_keypair = Some(Rc::clone(&exchange_keypair));
+        Ok(exchange_keypair)
+    }
+
+    /// Creates a new private forest and root directory for this session.
+    pub async fn init(
+        &mut self,
+    ) -> Result<(PrivateDirectoryHelper<'a>, AccessKey, Cid), WnfsUtilsError> {
+        let exchange_keypair = self.exchange_keypair()?;
+        PrivateDirectoryHelper::init_with_keypair(
+            &mut self.store,
+            self.wnfs_key.to_owned(),
+            exchange_keypair,
+        )
+        .await
+    }
+
+    /// Loads the private forest at `forest_cid` with this session's wnfs key. Sessions of
+    /// share handles open the shared directory in that forest instead.
+    pub async fn load(
+        &mut self,
+        forest_cid: Cid,
+    ) -> Result<PrivateDirectoryHelper<'a>, WnfsUtilsError> {
+        let exchange_keypair = self.exchange_keypair()?;
+        match &self.share {
+            Some(share) => {
+                PrivateDirectoryHelper::receive_share_with_keypair(
+                    &mut self.store,
+                    &share.sharer_did,
+                    share.counter,
+                    forest_cid,
+                    self.wnfs_key.to_owned(),
+                    exchange_keypair,
+                    share.writable,
+                )
+                .await
+            }
+            None => {
+                PrivateDirectoryHelper::load_with_keypair(
+                    &mut self.store,
+                    forest_cid,
+                    self.wnfs_key.to_owned(),
+                    exchange_keypair,
+                )
+                .await
+            }
+        }
+    }
+
+    /// Opens the latest directory shared with this session's owner by `sharer_did` in the
+    /// forest at `forest_cid`. See `PrivateDirectoryHelper::receive_share`.
+    pub async fn receive_share(
+        &mut self,
+        sharer_did: &str,
+        forest_cid: Cid,
+        writable: bool,
+    ) -> Result<PrivateDirectoryHelper<'a>, WnfsUtilsError> {
+        let exchange_keypair = self.exchange_keypair()?;
+        PrivateDirectoryHelper::receive_share_with_keypair(
+            &mut self.store,
+            sharer_did,
+            ShareCounter::LatestFrom(0),
+            forest_cid,
+            self.wnfs_key.to_owned(),
+            exchange_keypair,
+            writable,
+        )
+        .await
+    }
+
+    /// Opens the directory shared with this session's owner by `sharer_did` under share
+    /// `counter`, as returned by `share_path`.
+    pub async fn receive_share_at(
+        &mut self,
+        sharer_did: &str,
+        counter: u64,
+        forest_cid: Cid,
+        writable: bool,
+    ) -> Result<PrivateDirectoryHelper<'a>, WnfsUtilsError> {
+        let exchange_keypair = self.exchange_keypair()?;
+        PrivateDirectoryHelper::receive_share_with_keypair(
+            &mut self.store,
+            sharer_did,
+            ShareCounter::At(counter),
+            forest_cid,
+            self.wnfs_key.to_owned(),
+            exchange_keypair,
+            writable,
+        )
+        .await
     }
 }
-static mut STATE: Mutex<State> = Mutex::new(State {
-    initialized: false,
-    wnfs_key: Vec::new(),
-});
 
 pub struct PrivateDirectoryHelper<'a> {
     pub store: FFIFriendlyBlockStore<'a>,
-    forest: Rc<HamtForest>,
-    root_dir: Rc<PrivateDirectory>,
-    rng: ThreadRng,
+    pub(crate) forest: Rc<HamtForest>,
+    pub(crate) root_dir: Rc<PrivateDirectory>,
+    // Revision of the root directory the drive was shared at, the oldest one reachable
+    // when browsing history.
+    pub(crate) first_root_dir: Rc<PrivateDirectory>,
+    // Private directory of drive state that isn't part of the user's files, see `state`.
+    // `None` until state is first written.
+    pub(crate) state_dir: Option<Rc<PrivateDirectory>>,
+    pub(crate) rng: ThreadRng,
+    // Quota and usage counter of the drive, `None` without a quota, see `usage`.
+    pub(crate) quota: Option<Quota>,
+    // Set for handles of shares that were received read-only. Every mutation fails with
+    // `ReadOnly` before changing anything.
+    pub(crate) read_only: bool,
+    // For share handles, the share they were received through, see `share`.
+    pub(crate) share: Option<ShareOrigin>,
+    pub(crate) wnfs_key: Vec<u8>,
+    // Exchange keypair derived from the wnfs key. Deriving it generates an RSA key, so it is
+    // derived once and shared with the sessions and helpers opened from this one.
+    pub(crate) exchange_keypair: Rc<SeededExchangeKey>,
 }
 
 // Single root (private ref) implementation of the wnfs private directory using KVBlockStore.
@@ -63,292 +196,216 @@ impl<'a> PrivateDirectoryHelper<'a> {
     pub fn root_dir(&self) -> &Rc<PrivateDirectory> {
         &self.root_dir
     }
+    // Returns the session this helper was opened with, to reload other forest cids of the same drive.
+    // For share handles, the session opens the same share in other forest cids.
+    pub fn session(&self) -> WnfsSession<'a> {
+        WnfsSession {
+            store: self.store.to_owned(),
+            wnfs_key: self.wnfs_key.to_owned(),
+            share: self.share.clone(),
+            exchange_keypair: Some(Rc::clone(&self.exchange_keypair)),
+        }
+    }
+
     async fn reload(
-        store: &mut FFIFriendlyBlockStore<'a>,
+        session: &mut WnfsSession<'a>,
         cid: Cid,
-    ) -> Result<PrivateDirectoryHelper<'a>, String> {
-        let initialized: bool;
-        let wnfs_key: Vec<u8>;
-        unsafe {
-            initialized = STATE.lock().unwrap().initialized;
-            wnfs_key = STATE.lock().unwrap().wnfs_key.to_owned();
-        }
-        if initialized {
-            let helper_res =
-                PrivateDirectoryHelper::load_with_wnfs_key(store, cid, wnfs_key.to_owned()).await;
-            if helper_res.is_ok() {
-                Ok(helper_res.ok().unwrap())
-            } else {
-                trace!(
-                    "wnfsError in new: {:?}",
-                    helper_res.as_ref().err().unwrap().to_string()
-                );
-                Err(helper_res.err().unwrap().to_string())
-            }
-        } else {
-            Err("PrivateDirectoryHelper not initialized".into())
-        }
+    ) -> Result<PrivateDirectoryHelper<'a>, WnfsUtilsError> {
+        session.load(cid).await.map_err(wnfs_error("reload"))
     }
 
-    fn bytes_to_hex_str(bytes: &[u8]) -> String {
+    pub(crate) fn bytes_to_hex_str(bytes: &[u8]) -> String {
         bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
     }
 
+    // Validates the wnfs key and returns it as the seed of the exchange keypair.
+    pub(crate) fn wnfs_seed(wnfs_key: &[u8]) -> Result<[u8; 32], WnfsUtilsError> {
+        if wnfs_key.is_empty() {
+            return Err(WnfsUtilsError::InvalidKey("wnfskey is empty".into()));
+        }
+        wnfs_key.try_into().map_err(|_| {
+            WnfsUtilsError::InvalidKey(format!(
+                "wnfskey must be {} bytes, got {}",
+                WNFS_KEY_LENGTH,
+                wnfs_key.len()
+            ))
+        })
+    }
+
+    // Derives the exchange keypair seeded by `wnfs_key`.
+    pub(crate) fn derive_exchange_keypair(
+        wnfs_key: &[u8],
+    ) -> Result<Rc<SeededExchangeKey>, WnfsUtilsError> {
+        let seed = Self::wnfs_seed(wnfs_key)?;
+        let exchange_keypair =
+            SeededExchangeKey::from_seed(seed).map_err(wnfs_error("derive_exchange_keypair"))?;
+        Ok(Rc::new(exchange_keypair))
+    }
+
     async fn setup_seeded_keypair_access(
         forest: &mut Rc<HamtForest>,
         access_key: AccessKey,
         store: &mut FFIFriendlyBlockStore<'a>,
-        seed: [u8; 32],
-    ) -> Result<[u8; 32]> {
-        let root_did = Self::bytes_to_hex_str(&seed);
-        let exchange_keypair = SeededExchangeKey::from_seed(seed.clone())?;
-
-        // Store the public key inside some public WNFS.
-        // Building from scratch in this case. Would actually be stored next to the private forest usually.
-        let public_key_cid = exchange_keypair.store_public_key(store).await?;
-        let mut exchange_root = Rc::new(PublicDirectory::new(Utc::now()));
-        exchange_root
-            .write(
-                &["main".into(), "v1.exchange_key".into()],
-                public_key_cid,
-                Utc::now(),
-                store,
-            )
-            .await?;
-        let exchange_root = PublicLink::new(PublicNode::Dir(exchange_root));
-
+        wnfs_key: &[u8],
+        exchange_keypair: &SeededExchangeKey,
+    ) -> Result<()> {
         // The user identity's root DID. In practice this would be e.g. an ed25519 key used
         // for e.g. UCANs or key usually used for authenticating writes.
-
-        let counter = recipient::find_latest_share_counter(
-            0,
-            1000,
-            &exchange_keypair.encode_public_key(),
-            &root_did,
-            forest,
-            store,
-        )
-        .await?
-        .map(|x| x + 1)
-        .unwrap_or_default();
-
-        // Write the encrypted AccessKey into the forest
-        sharer::share::<PublicExchangeKey>(
+        let root_did = Self::bytes_to_hex_str(wnfs_key);
+        share_access_key(
             &access_key,
-            counter,
+            &exchange_keypair.encode_public_key(),
             &root_did,
-            exchange_root,
             forest,
             store,
         )
         .await?;
-        Ok(seed)
+        Ok(())
     }
 
-    async fn init(
+    pub(crate) async fn init(
         store: &mut FFIFriendlyBlockStore<'a>,
         wnfs_key: Vec<u8>,
-    ) -> Result<(PrivateDirectoryHelper<'a>, AccessKey, Cid), String> {
-        let rng = &mut thread_rng();
-        if wnfs_key.is_empty() {
-            let err = "wnfskey is empty".to_string();
-            trace!("wnfsError occured in init: {:?}", err);
-            return Err(err);
-        }
+    ) -> Result<(PrivateDirectoryHelper<'a>, AccessKey, Cid), WnfsUtilsError> {
+        let exchange_keypair =
+            Self::derive_exchange_keypair(&wnfs_key).map_err(wnfs_error("init"))?;
+        Self::init_with_keypair(store, wnfs_key, exchange_keypair).await
+    }
 
-        let forest_res = PrivateDirectoryHelper::create_private_forest(store.to_owned(), rng).await;
+    pub(crate) async fn init_with_keypair(
+        store: &mut FFIFriendlyBlockStore<'a>,
+        wnfs_key: Vec<u8>,
+        exchange_keypair: Rc<SeededExchangeKey>,
+    ) -> Result<(PrivateDirectoryHelper<'a>, AccessKey, Cid), WnfsUtilsError> {
+        let rng = &mut thread_rng();
 
-        if forest_res.is_ok() {
-            let (forest, _) = &mut forest_res.ok().unwrap();
-            let root_dir_res = PrivateDirectory::new_and_store(
-                &forest.empty_name(),
-                Utc::now(),
-                forest,
-                store,
-                rng,
-            )
-            .await;
+        let (forest, _) =
+            &mut PrivateDirectoryHelper::create_private_forest(store.to_owned(), rng).await?;
+        let root_dir = &mut PrivateDirectory::new_and_store(
+            &forest.empty_name(),
+            Utc::now(),
+            forest,
+            store,
+            rng,
+        )
+        .await
+        .map_err(wnfs_error("init"))?;
 
-            if root_dir_res.is_ok() {
-                // Private ref contains data and keys for fetching and decrypting the directory node in the private forest.
-                let root_dir = &mut root_dir_res.ok().unwrap();
-                let access_key = root_dir.as_node().store(forest, store, rng).await;
-                if access_key.is_ok() {
-                    let seed: [u8; 32] = wnfs_key.to_owned().try_into().expect("Length mismatch");
-                    let access_key_unwrapped = access_key.ok().unwrap();
-                    let seed_res = Self::setup_seeded_keypair_access(
-                        forest,
-                        access_key_unwrapped.to_owned(),
-                        store,
-                        seed,
-                    )
-                    .await;
-                    let forest_cid = PrivateDirectoryHelper::update_private_forest(
-                        store.to_owned(),
-                        forest.to_owned(),
-                    )
-                    .await;
-                    if forest_cid.is_ok() {
-                        unsafe {
-                            STATE.lock().unwrap().update(true, wnfs_key.to_owned());
-                        }
-                        Ok((
-                            Self {
-                                store: store.to_owned(),
-                                forest: forest.to_owned(),
-                                root_dir: root_dir.to_owned(),
-                                rng: rng.to_owned(),
-                            },
-                            access_key_unwrapped,
-                            forest_cid.unwrap(),
-                        ))
-                    } else {
-                        trace!(
-                            "wnfsError in init:setup_seeded_keypair_access : {:?}",
-                            seed_res.as_ref().err().unwrap().to_string()
-                        );
-                        Err(seed_res.err().unwrap().to_string())
-                    }
-                } else {
-                    trace!(
-                        "wnfsError in init: {:?}",
-                        access_key.as_ref().err().unwrap().to_string()
-                    );
-                    Err(access_key.err().unwrap().to_string())
-                }
-            } else {
-                trace!(
-                    "wnfsError occured in init: {:?}",
-                    root_dir_res.as_ref().to_owned().err().unwrap().to_string()
-                );
-                Err(root_dir_res.as_ref().to_owned().err().unwrap().to_string())
-            }
-        } else {
-            let err = forest_res.as_ref().to_owned().err().unwrap().to_string();
-            trace!("wnfsError occured in init: {:?}", err);
-            Err(err)
-        }
+        // Private ref contains data and keys for fetching and decrypting the directory node in the private forest.
+        let access_key = root_dir
+            .as_node()
+            .store(forest, store, rng)
+            .await
+            .map_err(wnfs_error("init"))?;
+        Self::setup_seeded_keypair_access(
+            forest,
+            access_key.to_owned(),
+            store,
+            &wnfs_key,
+            &exchange_keypair,
+        )
+        .await
+        .map_err(wnfs_error("init:setup_seeded_keypair_access"))?;
+        let forest_cid =
+            PrivateDirectoryHelper::update_private_forest(store.to_owned(), forest.to_owned())
+                .await?;
+        Ok((
+            Self {
+                store: store.to_owned(),
+                forest: forest.to_owned(),
+                root_dir: root_dir.to_owned(),
+                first_root_dir: root_dir.to_owned(),
+                state_dir: None,
+                rng: rng.to_owned(),
+                quota: None,
+                read_only: false,
+                share: None,
+                wnfs_key: wnfs_key.to_owned(),
+                exchange_keypair,
+            },
+            access_key,
+            forest_cid,
+        ))
     }
 
     pub async fn load_with_wnfs_key(
         store: &mut FFIFriendlyBlockStore<'a>,
         forest_cid: Cid,
         wnfs_key: Vec<u8>,
-    ) -> Result<PrivateDirectoryHelper<'a>, String> {
+    ) -> Result<PrivateDirectoryHelper<'a>, WnfsUtilsError> {
+        let exchange_keypair = Self::derive_exchange_keypair(&wnfs_key)
+            .map_err(wnfs_error("load_with_wnfs_key exchange_keypair_res"))?;
+        Self::load_with_keypair(store, forest_cid, wnfs_key, exchange_keypair).await
+    }
+
+    pub(crate) async fn load_with_keypair(
+        store: &mut FFIFriendlyBlockStore<'a>,
+        forest_cid: Cid,
+        wnfs_key: Vec<u8>,
+        exchange_keypair: Rc<SeededExchangeKey>,
+    ) -> Result<PrivateDirectoryHelper<'a>, WnfsUtilsError> {
         trace!("wnfsutils: load_with_wnfs_key started");
         let rng = &mut thread_rng();
-        let root_did: String;
-        let seed: [u8; 32];
-        if wnfs_key.is_empty() {
-            let err = "wnfskey is empty".to_string();
-            trace!("wnfsError occured in load_with_wnfs_key: {:?}", err);
-            return Err(err);
-        } else {
-            root_did = Self::bytes_to_hex_str(&wnfs_key);
-            seed = wnfs_key.to_owned().try_into().expect("Length mismatch");
-        }
-        let exchange_keypair_res = SeededExchangeKey::from_seed(seed);
-        if exchange_keypair_res.is_ok() {
-            let exchange_keypair = exchange_keypair_res.ok().unwrap();
-            trace!(
-                "wnfsutils: load_with_wnfs_key with forest_cid: {:?}",
-                forest_cid
-            );
-            let forest_res =
-                PrivateDirectoryHelper::load_private_forest(store.to_owned(), forest_cid).await;
-            if forest_res.is_ok() {
-                let forest = &mut forest_res.ok().unwrap();
-                // Re-load private node from forest
-                let counter_res = recipient::find_latest_share_counter(
-                    0,
-                    1000,
-                    &exchange_keypair.encode_public_key(),
-                    &root_did,
-                    forest,
-                    store,
-                )
-                .await;
-                if counter_res.is_ok() {
-                    let counter = counter_res.ok().unwrap().map(|x| x).unwrap_or_default();
-                    trace!("wnfsutils: load_with_wnfs_key with counter: {:?}", counter);
-                    let name = sharer::create_share_name(
-                        counter,
-                        &root_did,
-                        &exchange_keypair.encode_public_key(),
-                        forest,
-                    );
-                    let node_res =
-                        recipient::receive_share(&name, &exchange_keypair, forest, store).await;
-                    if node_res.is_ok() {
-                        let node = node_res.ok().unwrap();
-                        let latest_node = node.search_latest(forest, store).await;
-
-                        if latest_node.is_ok() {
-                            let latest_root_dir = latest_node.ok().unwrap().as_dir();
-                            if latest_root_dir.is_ok() {
-                                unsafe {
-                                    STATE.lock().unwrap().update(true, wnfs_key.to_owned());
-                                }
-                                Ok(Self {
-                                    store: store.to_owned(),
-                                    forest: forest.to_owned(),
-                                    root_dir: latest_root_dir.ok().unwrap(),
-                                    rng: rng.to_owned(),
-                                })
-                            } else {
-                                trace!(
-                                    "wnfsError in load_with_wnfs_key: {:?}",
-                                    latest_root_dir.as_ref().err().unwrap().to_string()
-                                );
-                                Err(latest_root_dir.err().unwrap().to_string())
-                            }
-                        } else {
-                            trace!(
-                                "wnfsError occured in load_with_wnfs_key: {:?}",
-                                latest_node.as_ref().to_owned().err().unwrap().to_string()
-                            );
-                            Err(latest_node.as_ref().to_owned().err().unwrap().to_string())
-                        }
-                    } else {
-                        let err = node_res.as_ref().to_owned().err().unwrap().to_string();
-                        trace!(
-                            "wnfsError occured in load_with_wnfs_key node_res: {:?}",
-                            err
-                        );
-                        Err(err)
-                    }
-                } else {
-                    let err = counter_res.as_ref().to_owned().err().unwrap().to_string();
-                    trace!(
-                        "wnfsError occured in load_with_wnfs_key counter_res: {:?}",
-                        err
-                    );
-                    Err(err)
-                }
-            } else {
-                let err = forest_res.as_ref().to_owned().err().unwrap().to_string();
-                trace!("wnfsError occured in load_with_wnfs_key: {:?}", err);
-                Err(err)
-            }
-        } else {
-            let err = exchange_keypair_res
-                .as_ref()
-                .to_owned()
-                .err()
-                .unwrap()
-                .to_string();
-            trace!(
-                "wnfsError occured in load_with_wnfs_key exchange_keypair_res: {:?}",
-                err
-            );
-            Err(err)
-        }
+        let root_did = Self::bytes_to_hex_str(&wnfs_key);
+        trace!(
+            "wnfsutils: load_with_wnfs_key with forest_cid: {:?}",
+            forest_cid
+        );
+        let forest =
+            &mut PrivateDirectoryHelper::load_private_forest(store.to_owned(), forest_cid).await?;
+
+        // Re-load private node from forest
+        let counter = recipient::find_latest_share_counter(
+            0,
+            1000,
+            &exchange_keypair.encode_public_key(),
+            &root_did,
+            forest,
+            store,
+        )
+        .await
+        .map_err(wnfs_error("load_with_wnfs_key counter_res"))?
+        .ok_or(WnfsUtilsError::ShareNotFound)
+        .map_err(wnfs_error("load_with_wnfs_key counter_res"))?;
+        trace!("wnfsutils: load_with_wnfs_key with counter: {:?}", counter);
+        let name = sharer::create_share_name(
+            counter,
+            &root_did,
+            &exchange_keypair.encode_public_key(),
+            forest,
+        );
+        let node = recipient::receive_share(&name, exchange_keypair.as_ref(), forest, store)
+            .await
+            .map_err(wnfs_error("load_with_wnfs_key node_res"))?;
+        let first_root_dir = node.as_dir().map_err(wnfs_error("load_with_wnfs_key"))?;
+        let latest_root_dir = node
+            .search_latest(forest, store)
+            .await
+            .map_err(wnfs_error("load_with_wnfs_key"))?
+            .as_dir()
+            .map_err(wnfs_error("load_with_wnfs_key"))?;
+        let state_dir = Self::load_state_dir(forest, store, &wnfs_key, &exchange_keypair).await?;
+        let mut helper = Self {
+            store: store.to_owned(),
+            forest: forest.to_owned(),
+            root_dir: latest_root_dir,
+            first_root_dir,
+            state_dir,
+            rng: rng.to_owned(),
+            quota: None,
+            read_only: false,
+            share: None,
+            wnfs_key: wnfs_key.to_owned(),
+            exchange_keypair,
+        };
+        helper.quota = helper.read_quota().await?;
+        Ok(helper)
     }
 
     async fn create_private_forest(
         store: FFIFriendlyBlockStore<'a>,
         rng: &mut ThreadRng,
-    ) -> Result<(Rc<HamtForest>, Cid), String> {
+    ) -> Result<(Rc<HamtForest>, Cid), WnfsUtilsError> {
         // Do a trusted setup for WNFS' name accumulators
         let setup = AccumulatorSetup::trusted(rng);
 
@@ -356,63 +413,80 @@ impl<'a> PrivateDirectoryHelper<'a> {
         let forest = &mut HamtForest::new_rc(setup);
 
         // Doing this will give us a single root CID
-        let private_root_cid = store.put_async_serializable(forest).await;
-        if private_root_cid.is_ok() {
-            let ret_forest = Rc::clone(forest);
-            Ok((ret_forest, private_root_cid.ok().unwrap()))
-        } else {
-            trace!(
-                "wnfsError occured in create_private_forest: {:?}",
-                private_root_cid.as_ref().err().unwrap()
-            );
-            Err(private_root_cid.err().unwrap().to_string())
-        }
+        let private_root_cid = store
+            .put_async_serializable(forest)
+            .await
+            .map_err(wnfs_error("create_private_forest"))?;
+        Ok((Rc::clone(forest), private_root_cid))
     }
 
-    async fn load_private_forest(
+    pub(crate) async fn load_private_forest(
         store: FFIFriendlyBlockStore<'a>,
         forest_cid: Cid,
-    ) -> Result<Rc<HamtForest>, String> {
+    ) -> Result<Rc<HamtForest>, WnfsUtilsError> {
         // Deserialize private forest from the blockstore.
-        let forest = store.get_deserializable::<HamtForest>(&forest_cid).await;
-        if forest.is_ok() {
-            Ok(Rc::new(forest.ok().unwrap()))
-        } else {
-            trace!(
-                "wnfsError occured in load__private_forest: {:?}",
-                forest.as_ref().err().unwrap()
-            );
-            Err(forest.err().unwrap().to_string())
-        }
+        let forest = store
+            .get_deserializable::<HamtForest>(&forest_cid)
+            .await
+            .map_err(wnfs_error("load_private_forest"))?;
+        Ok(Rc::new(forest))
     }
 
     pub async fn update_private_forest(
         store: FFIFriendlyBlockStore<'a>,
         forest: Rc<HamtForest>,
-    ) -> Result<Cid, String> {
+    ) -> Result<Cid, WnfsUtilsError> {
         // Serialize the private forest to DAG CBOR.
         // Doing this will give us a single root CID
-        let private_root_cid = store.put_async_serializable(&forest).await;
-        if private_root_cid.is_ok() {
-            Ok(private_root_cid.ok().unwrap())
-        } else {
-            trace!(
-                "wnfsError occured in create_private_forest: {:?}",
-                private_root_cid.as_ref().err().unwrap()
-            );
-            Err(private_root_cid.err().unwrap().to_string())
-        }
+        store
+            .put_async_serializable(&forest)
+            .await
+            .map_err(wnfs_error("update_private_forest"))
     }
 
     pub async fn write_file(
         &mut self,
+        path_segments: &[String],
+        content: Vec<u8>,
+        modification_time_seconds: i64,
+    ) -> Result<Cid, WnfsUtilsError> {
+        self.apply_write_file(path_segments, content, modification_time_seconds)
+            .await?;
+        self.store_root_dir("write_file").await
+    }
 
+    pub(crate) async fn apply_write_file(
+        &mut self,
         path_segments: &[String],
         content: Vec<u8>,
         modification_time_seconds: i64,
-    ) -> Result<Cid, String> {
-        let forest = &mut self.forest;
-        let root_dir = &mut self.root_dir;
+    ) -> Result<(), WnfsUtilsError> {
+        self.check_writable()?;
+        let replaced_bytes = self.quota_bytes_at(path_segments).await?;
+        let quota = self.quota;
+        self.charge_quota(replaced_bytes, content.len() as u64)
+            .map_err(wnfs_error("write_file"))?;
+        let modification_time_utc = Self::modification_time_utc(modification_time_seconds);
+        let result = self
+            .root_dir
+            .write(
+                path_segments,
+                true,
+                modification_time_utc,
+                content,
+                &mut self.forest,
+                &mut self.store,
+                &mut self.rng,
+            )
+            .await;
+        if result.is_err() {
+            self.quota = quota;
+        }
+        result.map_err(wnfs_error("write_file"))?;
+        Ok(())
+    }
+
+    fn modification_time_utc(modification_time_seconds: i64) -> DateTime<Utc> {
         let mut modification_time_utc: DateTime<Utc> = Utc::now();
         if modification_time_seconds > 0 {
             let naive_datetime = DateTime::from_timestamp(modification_time_seconds, 0)
@@ -420,294 +494,487 @@ impl<'a> PrivateDirectoryHelper<'a> {
                 .naive_utc();
             modification_time_utc = DateTime::from_naive_utc_and_offset(naive_datetime, Utc);
         }
-        let write_res = root_dir
-            .write(
+        modification_time_utc
+    }
+
+    // Stores the root node and the forest after a mutation and returns the new forest cid.
+    pub(crate) async fn store_root_dir(
+        &mut self,
+        caller: &'static str,
+    ) -> Result<Cid, WnfsUtilsError> {
+        self.check_writable()?;
+        if let Some(quota) = self.quota {
+            self.apply_write_quota(quota)
+                .await
+                .map_err(wnfs_error(caller))?;
+        }
+        // Private ref contains data and keys for fetching and decrypting the directory node in the private forest.
+        self.root_dir
+            .as_node()
+            .store(&mut self.forest, &mut self.store, &mut self.rng)
+            .await
+            .map_err(wnfs_error(caller))?;
+        if let Some(state_dir) = &self.state_dir {
+            state_dir
+                .as_node()
+                .store(&mut self.forest, &mut self.store, &mut self.rng)
+                .await
+                .map_err(wnfs_error(caller))?;
+        }
+        PrivateDirectoryHelper::update_private_forest(self.store.to_owned(), self.forest.to_owned())
+            .await
+            .map_err(wnfs_error(caller))
+    }
+
+    /// Writes a file by streaming its content from an async reader, so the whole file
+    /// never has to be held in memory. Content is chunked and encrypted block by block.
+    /// With a quota set, the content is buffered up to the bytes the quota still allows, so
+    /// that a write exceeding it is rejected before any block is stored.
+    pub async fn write_file_stream(
+        &mut self,
+        path_segments: &[String],
+        content: impl AsyncRead + Unpin,
+        modification_time_seconds: i64,
+    ) -> Result<Cid, WnfsUtilsError> {
+        self.apply_write_file_stream(path_segments, content, modification_time_seconds)
+            .await?;
+        self.store_root_dir("write_file_stream").await
+    }
+
+    pub(crate) async fn apply_write_file_stream(
+        &mut self,
+        path_segments: &[String],
+        mut content: impl AsyncRead + Unpin,
+        modification_time_seconds: i64,
+    ) -> Result<(), WnfsUtilsError> {
+        self.check_writable()?;
+        let Some(quota) = self.quota else {
+            return self
+                .set_file_content(
+                    path_segments,
+                    content,
+                    modification_time_seconds,
+                    "write_file_stream",
+                )
+                .await;
+        };
+        // The size of a stream is only known once it is read, so it is read up to the bytes
+        // the write may add, and a stream exceeding them is only counted, never stored.
+        let replaced_bytes = self.quota_bytes_at(path_segments).await?;
+        let allowed_bytes = quota
+            .quota_bytes
+            .saturating_sub(quota.used_bytes.saturating_sub(replaced_bytes))
+            .max(replaced_bytes);
+        let mut buffered = Vec::new();
+        (&mut content)
+            .take(allowed_bytes.saturating_add(1))
+            .read_to_end(&mut buffered)
+            .await
+            .map_err(wnfs_error("write_file_stream"))?;
+        let mut added_bytes = buffered.len() as u64;
+        if added_bytes > allowed_bytes {
+            added_bytes += io::copy(content, &mut io::sink())
+                .await
+                .map_err(wnfs_error("write_file_stream"))?;
+        }
+        self.charge_quota(replaced_bytes, added_bytes)
+            .map_err(wnfs_error("write_file_stream"))?;
+        let result = self
+            .set_file_content(
+                path_segments,
+                buffered.as_slice(),
+                modification_time_seconds,
+                "write_file_stream",
+            )
+            .await;
+        if result.is_err() {
+            self.quota = Some(quota);
+        }
+        result
+    }
+
+    // Replaces the content of the file at `path_segments`, creating it if needed.
+    async fn set_file_content(
+        &mut self,
+        path_segments: &[String],
+        content: impl AsyncRead + Unpin,
+        modification_time_seconds: i64,
+        caller: &'static str,
+    ) -> Result<(), WnfsUtilsError> {
+        let modification_time_utc = Self::modification_time_utc(modification_time_seconds);
+        let file = self
+            .root_dir
+            .open_file_mut(
                 path_segments,
                 true,
                 modification_time_utc,
-                content,
-                forest,
+                &mut self.forest,
                 &mut self.store,
                 &mut self.rng,
             )
-            .await;
-        if write_res.is_ok() {
-            // Private ref contains data and keys for fetching and decrypting the directory node in the private forest.
-            let access_key = root_dir
-                .as_node()
-                .store(forest, &mut self.store, &mut self.rng)
-                .await;
-            if access_key.is_ok() {
-                let forest_cid = PrivateDirectoryHelper::update_private_forest(
-                    self.store.to_owned(),
-                    forest.to_owned(),
-                )
-                .await;
-                if forest_cid.is_ok() {
-                    Ok(forest_cid.ok().unwrap())
-                } else {
-                    trace!(
-                        "wnfsError in write_file: {:?}",
-                        forest_cid.as_ref().err().unwrap().to_string()
-                    );
-                    Err(forest_cid.err().unwrap().to_string())
-                }
-            } else {
-                trace!(
-                    "wnfsError in write_file: {:?}",
-                    access_key.as_ref().err().unwrap().to_string()
-                );
-                Err(access_key.err().unwrap().to_string())
-            }
-        } else {
-            trace!(
-                "wnfsError in write_file: {:?}",
-                write_res.as_ref().err().unwrap().to_string()
-            );
-            Err(write_res.err().unwrap().to_string())
+            .await
+            .map_err(wnfs_error(caller))?;
+        file.set_content(
+            modification_time_utc,
+            content,
+            &mut self.forest,
+            &mut self.store,
+            &mut self.rng,
+        )
+        .await
+        .map_err(wnfs_error(caller))?;
+        Ok(())
+    }
+
+    /// Writes a file by streaming it from a local path.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub async fn write_file_stream_from_path(
+        &mut self,
+        path_segments: &[String],
+        filename: &String,
+    ) -> Result<Cid, WnfsUtilsError> {
+        let file = async_std::fs::File::open(filename)
+            .await
+            .map_err(wnfs_error("write_file_stream_from_path"))?;
+        let mut modification_time_seconds = 0;
+        if let Ok(modified) = file.metadata().await.and_then(|m| m.modified()) {
+            modification_time_seconds = DateTime::<Utc>::from(modified).timestamp();
         }
+        self.write_file_stream(path_segments, file, modification_time_seconds)
+            .await
+    }
+
+    /// Appends `content` to the end of an existing file. The file is re-encrypted as a whole,
+    /// see `apply_splice_file`.
+    pub async fn append_file(
+        &mut self,
+        path_segments: &[String],
+        content: Vec<u8>,
+        modification_time_seconds: i64,
+    ) -> Result<Cid, WnfsUtilsError> {
+        self.apply_splice_file(path_segments, None, content, modification_time_seconds)
+            .await?;
+        self.store_root_dir("append_file").await
+    }
+
+    /// Overwrites the bytes of an existing file starting at `offset` with `content`,
+    /// growing the file if needed. Writing past the end fills the gap with zeros. The file is
+    /// re-encrypted as a whole, see `apply_splice_file`.
+    pub async fn write_at(
+        &mut self,
+        path_segments: &[String],
+        offset: usize,
+        content: Vec<u8>,
+        modification_time_seconds: i64,
+    ) -> Result<Cid, WnfsUtilsError> {
+        self.apply_splice_file(
+            path_segments,
+            Some(offset),
+            content,
+            modification_time_seconds,
+        )
+        .await?;
+        self.store_root_dir("write_at").await
     }
 
-    pub async fn read_file(&mut self, path_segments: &[String]) -> Result<Vec<u8>, String> {
-        let forest = &mut self.forest;
-        let root_dir = &mut self.root_dir;
-        let res = root_dir
-            .read(path_segments, true, forest, &mut self.store)
+    // Writes `content` into an existing file at `offset`, or at its end if `offset` is `None`.
+    // The unchanged content is streamed block by block from the current revision of the file
+    // into the new one, so the file is never held in memory as a whole.
+    //
+    // Unchanged content blocks can't be reused: the wnfs revision this crate is pinned to
+    // encrypts every new content revision under a fresh key and keeps the key and the block
+    // labels private, so each splice re-encrypts the whole file. Reusing blocks needs an
+    // append or splice primitive in wnfs itself.
+    pub(crate) async fn apply_splice_file(
+        &mut self,
+        path_segments: &[String],
+        offset: Option<usize>,
+        content: Vec<u8>,
+        modification_time_seconds: i64,
+    ) -> Result<(), WnfsUtilsError> {
+        self.check_writable()?;
+        let end = match offset {
+            Some(offset) => Some(offset.checked_add(content.len()).ok_or_else(|| {
+                WnfsUtilsError::Other(format!(
+                    "splice_file: offset {} plus {} bytes overflows",
+                    offset,
+                    content.len()
+                ))
+            })?),
+            None => None,
+        };
+        let current_file = self
+            .get_node(path_segments)
+            .await
+            .map_err(wnfs_error("splice_file"))?
+            .as_file()
+            .map_err(wnfs_error("splice_file"))?;
+        let current_bytes = self.quota_bytes_at(path_segments).await?;
+        let spliced_bytes = match end {
+            Some(end) => current_bytes.max(end as u64),
+            None => current_bytes + content.len() as u64,
+        };
+        let quota = self.quota;
+        self.charge_quota(current_bytes, spliced_bytes)
+            .map_err(wnfs_error("splice_file"))?;
+        // Copy-on-write snapshots, so the current revision stays readable while it is replaced.
+        let current_forest = Rc::clone(&self.forest);
+        let current_store = self.store.to_owned();
+        let spliced = Box::pin(splice_content(
+            current_file.stream_content(0, &current_forest, &current_store),
+            offset,
+            content,
+        ))
+        .into_async_read();
+        let result = self
+            .set_file_content(
+                path_segments,
+                spliced,
+                modification_time_seconds,
+                "splice_file",
+            )
             .await;
-        if res.is_ok() {
-            let result = res.ok().unwrap();
-            Ok(result)
-        } else {
-            trace!(
-                "wnfsError occured in read_file: {:?} ",
-                res.as_ref().err().unwrap()
-            );
-            Err(res.err().unwrap().to_string())
+        if result.is_err() {
+            self.quota = quota;
+        }
+        result
+    }
+
+    /// Streams the decrypted content of a file, starting at the content block `index`,
+    /// into an async writer. Returns the number of bytes written.
+    pub async fn read_file_stream(
+        &mut self,
+        path_segments: &[String],
+        index: usize,
+        writer: impl AsyncWrite + Unpin,
+    ) -> Result<usize, WnfsUtilsError> {
+        let file = self
+            .get_node(path_segments)
+            .await
+            .map_err(wnfs_error("read_file_stream"))?
+            .as_file()
+            .map_err(wnfs_error("read_file_stream"))?;
+        self.stream_file_content(&file, index, writer).await
+    }
+
+    // Looks up the node at `path_segments`, failing with `NotFound` if there is none.
+    pub(crate) async fn get_node(
+        &self,
+        path_segments: &[String],
+    ) -> Result<PrivateNode, WnfsUtilsError> {
+        self.root_dir
+            .get_node(path_segments, true, &self.forest, &self.store)
+            .await?
+            .ok_or_else(|| WnfsUtilsError::NotFound(path_segments.join("/")))
+    }
+
+    // Writes the content blocks of `file` starting at block `index` into `writer`.
+    pub(crate) async fn stream_file_content(
+        &self,
+        file: &PrivateFile,
+        index: usize,
+        mut writer: impl AsyncWrite + Unpin,
+    ) -> Result<usize, WnfsUtilsError> {
+        let stream = file.stream_content(index, &self.forest, &self.store);
+        pin_mut!(stream);
+        let mut written = 0;
+        while let Some(block) = stream.next().await {
+            let block = block.map_err(wnfs_error("stream_file_content"))?;
+            writer
+                .write_all(&block)
+                .await
+                .map_err(wnfs_error("stream_file_content"))?;
+            written += block.len();
+        }
+        writer
+            .flush()
+            .await
+            .map_err(wnfs_error("stream_file_content"))?;
+        Ok(written)
+    }
+
+    /// Streams the decrypted content of a file into a local path.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub async fn read_filestream_to_path(
+        &mut self,
+        local_filename: &String,
+        path_segments: &[String],
+        index: usize,
+    ) -> Result<usize, WnfsUtilsError> {
+        let file = async_std::fs::File::create(local_filename)
+            .await
+            .map_err(wnfs_error("read_filestream_to_path"))?;
+        self.read_file_stream(path_segments, index, file).await
+    }
+
+    pub async fn read_file(&mut self, path_segments: &[String]) -> Result<Vec<u8>, WnfsUtilsError> {
+        self.root_dir
+            .read(path_segments, true, &self.forest, &self.store)
+            .await
+            .map_err(wnfs_path_error("read_file", path_segments))
+    }
+
+    /// Reads up to `len` bytes of a file starting at byte `offset`. Only the content blocks
+    /// covering the requested range are fetched and decrypted.
+    pub async fn read_file_range(
+        &mut self,
+        path_segments: &[String],
+        offset: usize,
+        len: usize,
+    ) -> Result<Vec<u8>, WnfsUtilsError> {
+        let file = self
+            .get_node(path_segments)
+            .await
+            .map_err(wnfs_error("read_file_range"))?
+            .as_file()
+            .map_err(wnfs_error("read_file_range"))?;
+
+        // Content is split into blocks of MAX_BLOCK_CONTENT_SIZE bytes; skip straight to the first
+        // block of the range.
+        let block_index = offset / MAX_BLOCK_CONTENT_SIZE;
+        let mut skip = offset % MAX_BLOCK_CONTENT_SIZE;
+        let mut content = Vec::with_capacity(len.min(MAX_BLOCK_CONTENT_SIZE));
+        let stream = file.stream_content(block_index, &self.forest, &self.store);
+        pin_mut!(stream);
+        while content.len() < len {
+            let block = match stream.next().await {
+                Some(block) => block.map_err(wnfs_error("read_file_range"))?,
+                None => break,
+            };
+            if skip >= block.len() {
+                skip -= block.len();
+                continue;
+            }
+            let remaining = &block[skip..];
+            skip = 0;
+            let take = remaining.len().min(len - content.len());
+            content.extend_from_slice(&remaining[..take]);
         }
+        Ok(content)
+    }
+
+    pub async fn mkdir(&mut self, path_segments: &[String]) -> Result<Cid, WnfsUtilsError> {
+        self.apply_mkdir(path_segments).await?;
+        self.store_root_dir("mkdir").await
     }
 
-    pub async fn mkdir(&mut self, path_segments: &[String]) -> Result<Cid, String> {
-        let forest = &mut self.forest;
-        let root_dir = &mut self.root_dir;
-        let res = root_dir
+    pub(crate) async fn apply_mkdir(
+        &mut self,
+        path_segments: &[String],
+    ) -> Result<(), WnfsUtilsError> {
+        self.check_writable()?;
+        self.root_dir
             .mkdir(
                 path_segments,
                 true,
                 Utc::now(),
-                forest,
+                &mut self.forest,
                 &mut self.store,
                 &mut self.rng,
             )
-            .await;
-        if res.is_ok() {
-            // Private ref contains data and keys for fetching and decrypting the directory node in the private forest.
-            let access_key = root_dir
-                .as_node()
-                .store(forest, &mut self.store, &mut self.rng)
-                .await;
-            if access_key.is_ok() {
-                let forest_cid = PrivateDirectoryHelper::update_private_forest(
-                    self.store.to_owned(),
-                    forest.to_owned(),
-                )
-                .await;
-                if forest_cid.is_ok() {
-                    Ok(forest_cid.ok().unwrap())
-                } else {
-                    trace!(
-                        "wnfsError in mkdir: {:?}",
-                        forest_cid.as_ref().err().unwrap().to_string()
-                    );
-                    Err(forest_cid.err().unwrap().to_string())
-                }
-            } else {
-                trace!(
-                    "wnfsError in mkdir: {:?}",
-                    access_key.as_ref().err().unwrap().to_string()
-                );
-                Err(access_key.err().unwrap().to_string())
-            }
-        } else {
-            trace!(
-                "wnfsError occured in mkdir: {:?}",
-                res.as_ref().err().unwrap()
-            );
-            Err(res.err().unwrap().to_string())
-        }
+            .await
+            .map_err(wnfs_error("mkdir"))?;
+        Ok(())
     }
 
-    pub async fn rm(&mut self, path_segments: &[String]) -> Result<Cid, String> {
-        let forest = &mut self.forest;
-        let root_dir = &mut self.root_dir;
-        let result = root_dir
-            .rm(path_segments, true, forest, &mut self.store)
-            .await;
-        if result.is_ok() {
-            // Private ref contains data and keys for fetching and decrypting the directory node in the private forest.
-            let access_key = root_dir
-                .as_node()
-                .store(forest, &mut self.store, &mut self.rng)
-                .await;
-            if access_key.is_ok() {
-                let forest_cid = PrivateDirectoryHelper::update_private_forest(
-                    self.store.to_owned(),
-                    forest.to_owned(),
-                )
-                .await;
-                if forest_cid.is_ok() {
-                    Ok(forest_cid.ok().unwrap())
-                } else {
-                    trace!(
-                        "wnfsError in result: {:?}",
-                        forest_cid.as_ref().err().unwrap().to_string()
-                    );
-                    Err(forest_cid.err().unwrap().to_string())
-                }
-            } else {
-                trace!(
-                    "wnfsError in result: {:?}",
-                    access_key.as_ref().err().unwrap().to_string()
-                );
-                Err(access_key.err().unwrap().to_string())
-            }
-        } else {
-            trace!(
-                "wnfsError occured in rm result: {:?}",
-                result.as_ref().err().unwrap()
-            );
-            Err(result.err().unwrap().to_string())
-        }
+    pub async fn rm(&mut self, path_segments: &[String]) -> Result<Cid, WnfsUtilsError> {
+        self.apply_rm(path_segments).await?;
+        self.store_root_dir("rm").await
+    }
+
+    pub(crate) async fn apply_rm(
+        &mut self,
+        path_segments: &[String],
+    ) -> Result<(), WnfsUtilsError> {
+        self.check_writable()?;
+        let removed_bytes = self.quota_bytes_at(path_segments).await?;
+        self.root_dir
+            .rm(path_segments, true, &mut self.forest, &mut self.store)
+            .await
+            .map_err(wnfs_path_error("rm", path_segments))?;
+        self.charge_quota(removed_bytes, 0)
     }
 
     pub async fn mv(
         &mut self,
         source_path_segments: &[String],
         target_path_segments: &[String],
-    ) -> Result<Cid, String> {
-        let forest = &mut self.forest;
-        let root_dir = &mut self.root_dir;
-        let mv_result = root_dir
+    ) -> Result<Cid, WnfsUtilsError> {
+        self.apply_mv(source_path_segments, target_path_segments)
+            .await?;
+        self.store_root_dir("mv").await
+    }
+
+    pub(crate) async fn apply_mv(
+        &mut self,
+        source_path_segments: &[String],
+        target_path_segments: &[String],
+    ) -> Result<(), WnfsUtilsError> {
+        self.check_writable()?;
+        let replaced_bytes = self.quota_bytes_at(target_path_segments).await?;
+        self.root_dir
             .basic_mv(
                 source_path_segments,
                 target_path_segments,
                 true,
                 Utc::now(),
-                forest,
+                &mut self.forest,
                 &mut self.store,
                 &mut self.rng,
             )
-            .await;
-        if mv_result.is_ok() {
-            // Private ref contains data and keys for fetching and decrypting the directory node in the private forest.
-            let access_key = root_dir
-                .as_node()
-                .store(forest, &mut self.store, &mut self.rng)
-                .await;
-            if access_key.is_ok() {
-                let forest_cid = PrivateDirectoryHelper::update_private_forest(
-                    self.store.to_owned(),
-                    forest.to_owned(),
-                )
-                .await;
-                if forest_cid.is_ok() {
-                    Ok(forest_cid.ok().unwrap())
-                } else {
-                    trace!(
-                        "wnfsError in mv_result: {:?}",
-                        forest_cid.as_ref().err().unwrap().to_string()
-                    );
-                    Err(forest_cid.err().unwrap().to_string())
-                }
-            } else {
-                trace!(
-                    "wnfsError in mv_result: {:?}",
-                    access_key.as_ref().err().unwrap().to_string()
-                );
-                Err(access_key.err().unwrap().to_string())
-            }
-        } else {
-            trace!(
-                "wnfsError occured in mv mv_result: {:?}",
-                mv_result.as_ref().err().unwrap()
-            );
-            Err(mv_result.err().unwrap().to_string())
-        }
+            .await
+            .map_err(wnfs_path_error("mv", source_path_segments))?;
+        self.charge_quota(replaced_bytes, 0)
     }
 
     pub async fn cp(
         &mut self,
         source_path_segments: &[String],
         target_path_segments: &[String],
-    ) -> Result<Cid, String> {
-        let forest = &mut self.forest;
-        let root_dir = &mut self.root_dir;
-        let cp_result = root_dir
+    ) -> Result<Cid, WnfsUtilsError> {
+        self.apply_cp(source_path_segments, target_path_segments)
+            .await?;
+        self.store_root_dir("cp").await
+    }
+
+    pub(crate) async fn apply_cp(
+        &mut self,
+        source_path_segments: &[String],
+        target_path_segments: &[String],
+    ) -> Result<(), WnfsUtilsError> {
+        self.check_writable()?;
+        let copied_bytes = self.quota_bytes_at(source_path_segments).await?;
+        let replaced_bytes = self.quota_bytes_at(target_path_segments).await?;
+        let quota = self.quota;
+        self.charge_quota(replaced_bytes, copied_bytes)
+            .map_err(wnfs_error("cp"))?;
+        let result = self
+            .root_dir
             .cp(
                 source_path_segments,
                 target_path_segments,
                 true,
                 Utc::now(),
-                forest,
+                &mut self.forest,
                 &mut self.store,
                 &mut self.rng,
             )
             .await;
-        if cp_result.is_ok() {
-            // Private ref contains data and keys for fetching and decrypting the directory node in the private forest.
-            let access_key = root_dir
-                .as_node()
-                .store(forest, &mut self.store, &mut self.rng)
-                .await;
-            if access_key.is_ok() {
-                let forest_cid = PrivateDirectoryHelper::update_private_forest(
-                    self.store.to_owned(),
-                    forest.to_owned(),
-                )
-                .await;
-                if forest_cid.is_ok() {
-                    Ok(forest_cid.ok().unwrap())
-                } else {
-                    trace!(
-                        "wnfsError in cp_result: {:?}",
-                        forest_cid.as_ref().err().unwrap().to_string()
-                    );
-                    Err(forest_cid.err().unwrap().to_string())
-                }
-            } else {
-                trace!(
-                    "wnfsError in cp_result: {:?}",
-                    access_key.as_ref().err().unwrap().to_string()
-                );
-                Err(access_key.err().unwrap().to_string())
-            }
-        } else {
-            trace!(
-                "wnfsError occured in cp cp_result: {:?}",
-                cp_result.as_ref().err().unwrap()
-            );
-            Err(cp_result.err().unwrap().to_string())
+        if result.is_err() {
+            self.quota = quota;
         }
+        result.map_err(wnfs_path_error("cp", source_path_segments))?;
+        Ok(())
     }
 
     pub async fn ls_files(
         &mut self,
         path_segments: &[String],
-    ) -> Result<Vec<(String, Metadata)>, String> {
-        let forest = &mut self.forest;
-        let root_dir = &mut self.root_dir;
-        let res = root_dir
-            .ls(path_segments, true, forest, &mut self.store)
-            .await;
-        if res.is_ok() {
-            let result = res.ok().unwrap();
-            Ok(result)
-        } else {
-            trace!(
-                "wnfsError occured in ls_files: {:?}",
-                res.as_ref().err().unwrap().to_string()
-            );
-            Err(res.err().unwrap().to_string())
-        }
+    ) -> Result<Vec<(String, Metadata)>, WnfsUtilsError> {
+        self.root_dir
+            .ls(path_segments, true, &self.forest, &self.store)
+            .await
+            .map_err(wnfs_path_error("ls_files", path_segments))
     }
 }
 
@@ -716,7 +983,7 @@ impl<'a> PrivateDirectoryHelper<'a> {
     pub async fn init_async(
         store: &mut FFIFriendlyBlockStore<'a>,
         wnfs_key: Vec<u8>,
-    ) -> Result<(PrivateDirectoryHelper<'a>, AccessKey, Cid), String> {
+    ) -> Result<(PrivateDirectoryHelper<'a>, AccessKey, Cid), WnfsUtilsError> {
         PrivateDirectoryHelper::init(store, wnfs_key).await
     }
 
@@ -724,15 +991,15 @@ impl<'a> PrivateDirectoryHelper<'a> {
         store: &mut FFIFriendlyBlockStore<'a>,
         forest_cid: Cid,
         wnfs_key: Vec<u8>,
-    ) -> Result<PrivateDirectoryHelper<'a>, String> {
+    ) -> Result<PrivateDirectoryHelper<'a>, WnfsUtilsError> {
         PrivateDirectoryHelper::load_with_wnfs_key(store, forest_cid, wnfs_key).await
     }
 
     pub async fn reload_async(
-        store: &mut FFIFriendlyBlockStore<'a>,
+        session: &mut WnfsSession<'a>,
         forest_cid: Cid,
-    ) -> Result<PrivateDirectoryHelper<'a>, String> {
-        PrivateDirectoryHelper::reload(store, forest_cid).await
+    ) -> Result<PrivateDirectoryHelper<'a>, WnfsUtilsError> {
+        PrivateDirectoryHelper::reload(session, forest_cid).await
     }
 
     pub async fn write_file_async(
@@ -740,42 +1007,95 @@ impl<'a> PrivateDirectoryHelper<'a> {
         path_segments: &[String],
         content: Vec<u8>,
         modification_time_seconds: i64,
-    ) -> Result<Cid, String> {
-        self.write_file(path_segments, content, modification_time_seconds).await
+    ) -> Result<Cid, WnfsUtilsError> {
+        self.write_file(path_segments, content, modification_time_seconds)
+            .await
     }
-    
-    pub async fn read_file_async(&mut self, path_segments: &[String]) -> Result<Vec<u8>, String> {
+
+    pub async fn read_file_async(
+        &mut self,
+        path_segments: &[String],
+    ) -> Result<Vec<u8>, WnfsUtilsError> {
         self.read_file(path_segments).await
     }
-    
-    pub async fn mkdir_async(&mut self, path_segments: &[String]) -> Result<Cid, String> {
+
+    pub async fn read_file_range_async(
+        &mut self,
+        path_segments: &[String],
+        offset: usize,
+        len: usize,
+    ) -> Result<Vec<u8>, WnfsUtilsError> {
+        self.read_file_range(path_segments, offset, len).await
+    }
+
+    pub async fn write_file_stream_async(
+        &mut self,
+        path_segments: &[String],
+        content: impl AsyncRead + Unpin,
+        modification_time_seconds: i64,
+    ) -> Result<Cid, WnfsUtilsError> {
+        self.write_file_stream(path_segments, content, modification_time_seconds)
+            .await
+    }
+
+    pub async fn append_file_async(
+        &mut self,
+        path_segments: &[String],
+        content: Vec<u8>,
+        modification_time_seconds: i64,
+    ) -> Result<Cid, WnfsUtilsError> {
+        self.append_file(path_segments, content, modification_time_seconds)
+            .await
+    }
+
+    pub async fn write_at_async(
+        &mut self,
+        path_segments: &[String],
+        offset: usize,
+        content: Vec<u8>,
+        modification_time_seconds: i64,
+    ) -> Result<Cid, WnfsUtilsError> {
+        self.write_at(path_segments, offset, content, modification_time_seconds)
+            .await
+    }
+
+    pub async fn read_file_stream_async(
+        &mut self,
+        path_segments: &[String],
+        index: usize,
+        writer: impl AsyncWrite + Unpin,
+    ) -> Result<usize, WnfsUtilsError> {
+        self.read_file_stream(path_segments, index, writer).await
+    }
+
+    pub async fn mkdir_async(&mut self, path_segments: &[String]) -> Result<Cid, WnfsUtilsError> {
         self.mkdir(path_segments).await
     }
-    
+
     pub async fn mv_async(
         &mut self,
         source_path_segments: &[String],
         target_path_segments: &[String],
-    ) -> Result<Cid, String> {
+    ) -> Result<Cid, WnfsUtilsError> {
         self.mv(source_path_segments, target_path_segments).await
     }
-    
+
     pub async fn cp_async(
         &mut self,
         source_path_segments: &[String],
         target_path_segments: &[String],
-    ) -> Result<Cid, String> {
+    ) -> Result<Cid, WnfsUtilsError> {
         self.cp(source_path_segments, target_path_segments).await
     }
-    
-    pub async fn rm_async(&mut self, path_segments: &[String]) -> Result<Cid, String> {
+
+    pub async fn rm_async(&mut self, path_segments: &[String]) -> Result<Cid, WnfsUtilsError> {
         self.rm(path_segments).await
     }
-    
+
     pub async fn ls_files_async(
         &mut self,
         path_segments: &[String],
-    ) -> Result<Vec<(String, Metadata)>, String> {
+    ) -> Result<Vec<(String, Metadata)>, WnfsUtilsError> {
         self.ls_files(path_segments).await
     }
 
@@ -788,7 +1108,172 @@ impl<'a> PrivateDirectoryHelper<'a> {
     }
 }
 
-struct SeededExchangeKey(RsaPrivateKey);
+// Logs a failed operation and converts its error into a `WnfsUtilsError`.
+pub(crate) fn wnfs_error<E: Into<WnfsUtilsError>>(
+    caller: &'static str,
+) -> impl Fn(E) -> WnfsUtilsError {
+    move |err| {
+        let err = err.into();
+        trace!("wnfsError occured in {}: {:?}", caller, err.to_string());
+        err
+    }
+}
+
+// Like `wnfs_error`, but reports `path_segments` as the missing path of a `NotFound` error,
+// which wnfs raises without one.
+pub(crate) fn wnfs_path_error<'p, E: Into<WnfsUtilsError>>(
+    caller: &'static str,
+    path_segments: &'p [String],
+) -> impl Fn(E) -> WnfsUtilsError + 'p {
+    move |err| match wnfs_error(caller)(err) {
+        WnfsUtilsError::NotFound(path) if path.is_empty() => {
+            WnfsUtilsError::NotFound(path_segments.join("/"))
+        }
+        err => err,
+    }
+}
+
+// Writes `access_key` into the forest, encrypted for the exchange key with the modulus
+// `recipient_exchange_key`, under the next free share counter of `sharer_did` for that key.
+// Returns the counter the recipient needs to find the share.
+pub(crate) async fn share_access_key(
+    access_key: &AccessKey,
+    recipient_exchange_key: &[u8],
+    sharer_did: &str,
+    forest: &mut Rc<HamtForest>,
+    store: &mut FFIFriendlyBlockStore<'_>,
+) -> Result<u64> {
+    let exchange_root = exchange_root(recipient_exchange_key, store).await?;
+    let counter = recipient::find_latest_share_counter(
+        0,
+        1000,
+        recipient_exchange_key,
+        sharer_did,
+        forest,
+        store,
+    )
+    .await?
+    .map(|x| x + 1)
+    .unwrap_or_default();
+
+    // Write the encrypted AccessKey into the forest
+    sharer::share::<PublicExchangeKey>(
+        access_key,
+        counter,
+        sharer_did,
+        exchange_root,
+        forest,
+        store,
+    )
+    .await?;
+    Ok(counter)
+}
+
+// Marks share `counter` of `sharer_did` for the exchange key `recipient_exchange_key` as
+// writable, by writing `access_key` a second time under the write grant label of
+// `sharer_did` and the same counter.
+pub(crate) async fn grant_write_access(
+    access_key: &AccessKey,
+    counter: u64,
+    recipient_exchange_key: &[u8],
+    sharer_did: &str,
+    forest: &mut Rc<HamtForest>,
+    store: &mut FFIFriendlyBlockStore<'_>,
+) -> Result<()> {
+    let exchange_root = exchange_root(recipient_exchange_key, store).await?;
+    sharer::share::<PublicExchangeKey>(
+        access_key,
+        counter,
+        &write_grant_did(sharer_did),
+        exchange_root,
+        forest,
+        store,
+    )
+    .await
+}
+
+// Label write grants of `sharer_did` are written under, see `grant_write_access`.
+pub(crate) fn write_grant_did(sharer_did: &str) -> String {
+    format!("{}:write", sharer_did)
+}
+
+// Public directory holding the exchange key with the modulus `recipient_exchange_key`, which
+// wnfs looks the key up in when encrypting a share.
+async fn exchange_root(
+    recipient_exchange_key: &[u8],
+    store: &mut FFIFriendlyBlockStore<'_>,
+) -> Result<PublicLink> {
+    // Store the public key inside some public WNFS.
+    // Building from scratch in this case. Would actually be stored next to the private forest usually.
+    let public_key_cid = store
+        .put_block(recipient_exchange_key.to_vec(), CODEC_RAW)
+        .await?;
+    let mut exchange_root = Rc::new(PublicDirectory::new(Utc::now()));
+    exchange_root
+        .write(
+            &["main".into(), "v1.exchange_key".into()],
+            public_key_cid,
+            Utc::now(),
+            store,
+        )
+        .await?;
+    Ok(PublicLink::new(PublicNode::Dir(exchange_root)))
+}
+
+// Replaces the bytes of `blocks` from `offset` on with `content`, or appends `content` if
+// `offset` is `None`. Content starting past the end is preceded by zeros, emitted in chunks of
+// `MAX_BLOCK_CONTENT_SIZE` so a large gap is never held in memory at once.
+fn splice_content(
+    blocks: impl Stream<Item = Result<Vec<u8>>>,
+    offset: Option<usize>,
+    content: Vec<u8>,
+) -> impl Stream<Item = std::io::Result<Vec<u8>>> {
+    let position = Rc::new(Cell::new(0usize));
+    let content = Rc::new(content);
+    let spliced = {
+        let position = Rc::clone(&position);
+        let content = Rc::clone(&content);
+        blocks.map(move |block| {
+            let block = block.map_err(std::io::Error::other)?;
+            let block_start = position.get();
+            let block_end = block_start + block.len();
+            position.set(block_end);
+            let Some(offset) = offset else {
+                return Ok(block);
+            };
+            let end = offset
+                .checked_add(content.len())
+                .ok_or_else(|| std::io::Error::other("splice offset overflows"))?;
+            let mut spliced = Vec::with_capacity(block.len());
+            spliced.extend_from_slice(&block[..offset.clamp(block_start, block_end) - block_start]);
+            if (block_start..block_end).contains(&offset) {
+                spliced.extend_from_slice(&content);
+            }
+            spliced.extend_from_slice(&block[end.clamp(block_start, block_end) - block_start..]);
+            Ok(spliced)
+        })
+    };
+    let tail = stream::once(async move {
+        let size = position.get();
+        let offset = offset.unwrap_or(size);
+        let gap = offset.saturating_sub(size);
+        let zeros = stream::iter(
+            (0..gap)
+                .step_by(MAX_BLOCK_CONTENT_SIZE)
+                .map(move |start| Ok(vec![0; (gap - start).min(MAX_BLOCK_CONTENT_SIZE)])),
+        );
+        let tail = if offset >= size {
+            content.to_vec()
+        } else {
+            Vec::new()
+        };
+        zeros.chain(stream::once(async move { Ok(tail) }))
+    })
+    .flatten();
+    spliced.chain(tail)
+}
+
+pub(crate) struct SeededExchangeKey(RsaPrivateKey);
 
 struct PublicExchangeKey(RsaPublicKey);
 
@@ -799,10 +1284,6 @@ impl SeededExchangeKey {
         Ok(Self(private_key))
     }
 
-    pub async fn store_public_key(&self, store: &impl BlockStore) -> Result<Cid> {
-        store.put_block(self.encode_public_key(), CODEC_RAW).await
-    }
-
     pub fn encode_public_key(&self) -> Vec<u8> {
         self.0.n().to_bytes_be()
     }
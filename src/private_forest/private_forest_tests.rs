@@ -1,15 +1,17 @@
 use wnfs::common::CODEC_DAG_CBOR;
 
 use crate::blockstore::FFIFriendlyBlockStore;
+use crate::error::WnfsUtilsError;
 use crate::kvstore::KVBlockStore;
-use crate::private_forest::PrivateDirectoryHelper;
-use libipld::Cid;
+use crate::memorystore::MemoryBlockStore;
+use crate::private_forest::{PrivateDirectoryHelper, WnfsSession};
 use rand::RngCore;
 use std::fs::{read, File};
 use std::io::Read;
 use std::io::Write;
 use std::path::Path;
 use std::path::PathBuf;
+use std::rc::Rc;
 use tempfile::NamedTempFile;
 
 fn generate_dummy_data(size: usize) -> Vec<u8> {
@@ -70,9 +72,8 @@ async fn iboverall() {
         .await;
     assert_eq!(content.ok(), None);
     println!("**************************reload test*****************");
-    let helper_reloaded = &mut PrivateDirectoryHelper::reload(blockstore, cid)
-        .await
-        .unwrap();
+    let session = &mut helper.session();
+    let helper_reloaded = &mut PrivateDirectoryHelper::reload(session, cid).await.unwrap();
     let cid_reloaded = helper_reloaded
         .write_file(
             &["root".into(), "hello2".into(), "world.txt".into()],
@@ -124,6 +125,11 @@ async fn test_stream() {
     let mut file = File::create(filename).unwrap();
     file.write_all(&vec![0u8; file_size]).unwrap();
 
+    let write_res = helper
+        .write_file_stream_from_path(&path_segments, &filename.to_string())
+        .await;
+    assert!(write_res.is_ok(), "Writing the file failed!");
+
     let read_res = helper
         .read_filestream_to_path(&read_filename.to_string(), &path_segments, 0)
         .await;
@@ -235,6 +241,11 @@ async fn test_large_file_write_stream() {
     async_std::fs::write(tmp_file.path(), &data).await.unwrap();
     let path_buf: PathBuf = tmp_file.path().to_path_buf();
     let path_string: String = path_buf.to_string_lossy().into_owned();
+    let cid = helper
+        .write_file_stream_from_path(&["root".into(), "file_stream1.bin".into()], &path_string)
+        .await
+        .unwrap();
+    println!("cid: {:?}", cid);
 
     let ls_result = helper.ls_files(&["root".into()]).await.unwrap();
     println!("ls: {:?}", ls_result);
@@ -247,6 +258,11 @@ async fn test_large_file_write_stream() {
     async_std::fs::write(tmp_file.path(), &data).await.unwrap();
     let path_buf: PathBuf = tmp_file.path().to_path_buf();
     let path_string: String = path_buf.to_string_lossy().into_owned();
+    let cid = helper
+        .write_file_stream_from_path(&["root".into(), "file_stream2.bin".into()], &path_string)
+        .await
+        .unwrap();
+    println!("cid: {:?}", cid);
 
     let ls_result = helper.ls_files(&["root".into()]).await.unwrap();
     println!("ls: {:?}", ls_result);
@@ -259,6 +275,11 @@ async fn test_large_file_write_stream() {
     async_std::fs::write(tmp_file.path(), &data).await.unwrap();
     let path_buf: PathBuf = tmp_file.path().to_path_buf();
     let path_string: String = path_buf.to_string_lossy().into_owned();
+    let cid = helper
+        .write_file_stream_from_path(&["root".into(), "file_stream3.bin".into()], &path_string)
+        .await
+        .unwrap();
+    println!("cid: {:?}", cid);
 
     let ls_result = helper.ls_files(&["root".into()]).await.unwrap();
     println!("ls: {:?}", ls_result);
@@ -271,6 +292,14 @@ async fn test_large_file_write_stream() {
     async_std::fs::write(tmp_file.path(), &data).await.unwrap();
     let path_buf: PathBuf = tmp_file.path().to_path_buf();
     let path_string: String = path_buf.to_string_lossy().into_owned();
+    let cid = helper
+        .write_file_stream_from_path(
+            &["root".into(), "large_file_stream.bin".into()],
+            &path_string,
+        )
+        .await
+        .unwrap();
+    println!("cid: {:?}", cid);
 
     let ls_result = helper.ls_files(&["root".into()]).await.unwrap();
     println!("ls: {:?}", ls_result);
@@ -313,6 +342,14 @@ async fn test_large_file_write_stream() {
     async_std::fs::write(tmp_file.path(), &data).await.unwrap();
     let path_buf: PathBuf = tmp_file.path().to_path_buf();
     let path_string: String = path_buf.to_string_lossy().into_owned();
+    let cid = helper
+        .write_file_stream_from_path(
+            &["root".into(), "large_file_stream2.bin".into()],
+            &path_string,
+        )
+        .await
+        .unwrap();
+    println!("cid: {:?}", cid);
 
     let ls_result = helper.ls_files(&["root".into()]).await.unwrap();
     println!("ls: {:?}", ls_result);
@@ -374,413 +411,6 @@ async fn test_large_file_write_stream() {
     assert_eq!(content, b"hello, world!".to_vec());
 }
 
-#[test]
-fn synced_test_large_file_write_stream() {
-    let itteration = 2;
-    let reload_itteration = 15;
-    let empty_key: Vec<u8> = vec![0; 32];
-    let store = KVBlockStore::new(
-        String::from("./tmp/synced_test_large_file_write_stream"),
-        CODEC_DAG_CBOR,
-    );
-    let blockstore = &mut FFIFriendlyBlockStore::new(Box::new(store));
-    let (helper, access_key, cid) =
-        &mut PrivateDirectoryHelper::synced_init(blockstore, empty_key.to_owned()).unwrap();
-
-    println!("cid: {:?}", cid);
-    println!("access_key: {:?}", access_key.to_owned());
-    let mut cid: Cid;
-
-    for i in 1..=itteration {
-        let path = vec!["root".into(), format!("test_{}", i).into()];
-        cid = helper.synced_mkdir(&path).unwrap();
-        println!("CID for mkdir test_{}: {:?}", i, cid);
-    }
-
-    for i in 1..=itteration {
-        println!(
-            "*******************Starting write iteration {}******************",
-            i
-        );
-
-        // Generate first dummy 1MB payload
-        let mut data = generate_dummy_data(1 * 1024 * 1024); // 1MB in bytes
-        rand::thread_rng().fill_bytes(&mut data);
-        let tmp_file = NamedTempFile::new().unwrap();
-        async_std::task::block_on(async {
-            async_std::fs::write(tmp_file.path(), &data).await.unwrap();
-        });
-    }
-
-    let ls_result: Vec<(String, wnfs::common::Metadata)> =
-        helper.synced_ls_files(&["root".into()]).unwrap();
-    println!("ls: {:?}", ls_result);
-    let filenames_from_ls: Vec<String> = ls_result.iter().map(|(name, _)| name.clone()).collect();
-
-    let mut found = true;
-    for i in 1..=itteration {
-        let file_name = format!("file_stream{}.bin", i);
-        if !filenames_from_ls.contains(&file_name) {
-            found = false;
-            break;
-        }
-    }
-
-    assert!(found, "Not all expected files are present");
-
-    // Generate a dummy 100MB payload
-    let mut data = generate_dummy_data(100 * 1024 * 1024); // 1000MB in bytes
-    rand::thread_rng().fill_bytes(&mut data);
-    let tmp_file = NamedTempFile::new().unwrap();
-    async_std::task::block_on(async {
-        async_std::fs::write(tmp_file.path(), &data).await.unwrap();
-    });
-    let path_buf: PathBuf = tmp_file.path().to_path_buf();
-    let path_string: String = path_buf.to_string_lossy().into_owned();
-
-    let ls_result = helper.synced_ls_files(&["root".into()]).unwrap();
-    println!("ls: {:?}", ls_result);
-    assert!(ls_result
-        .iter()
-        .any(|item| item.0 == "large_file_stream.bin"));
-
-    let tmp_file_read = NamedTempFile::new().unwrap();
-    let path_buf_read: PathBuf = tmp_file_read.path().to_path_buf();
-    let path_string_read: String = path_buf_read.to_string_lossy().into_owned();
-    helper
-        .synced_read_filestream_to_path(
-            &path_string_read,
-            &["root".into(), "large_file_stream.bin".into()],
-            0,
-        )
-        .unwrap();
-
-    let mut file1 = File::open(tmp_file.path()).unwrap();
-    let mut file2 = File::open(tmp_file_read.path()).unwrap();
-
-    let metadata1 = file1.metadata().unwrap();
-    let metadata2 = file2.metadata().unwrap();
-    println!(
-        "original filesize: {:?} and read size: {:?}",
-        metadata1.len(),
-        metadata2.len()
-    );
-    assert_eq!(metadata1.len(), metadata2.len(), "File sizes do not match");
-
-    let mut content1 = Vec::new();
-    let mut content2 = Vec::new();
-
-    file1.read_to_end(&mut content1).unwrap();
-    file2.read_to_end(&mut content2).unwrap();
-    assert_eq!(content1, content2);
-
-    println!("read_file_stream_from_path checks done");
-
-    // Generate second dummy 60MB payload
-    let mut data = generate_dummy_data(60 * 1024 * 1024); // 1000MB in bytes
-    rand::thread_rng().fill_bytes(&mut data);
-    let tmp_file = NamedTempFile::new().unwrap();
-    async_std::task::block_on(async {
-        async_std::fs::write(tmp_file.path(), &data).await.unwrap();
-    });
-    let path_buf: PathBuf = tmp_file.path().to_path_buf();
-    let path_string: String = path_buf.to_string_lossy().into_owned();
-
-    let ls_result = helper.synced_ls_files(&["root".into()]).unwrap();
-    println!("ls: {:?}", ls_result);
-    assert!(ls_result
-        .iter()
-        .any(|item| item.0 == "large_file_stream2.bin"));
-
-    let tmp_file_read = NamedTempFile::new().unwrap();
-    let path_buf_read: PathBuf = tmp_file_read.path().to_path_buf();
-    let path_string_read: String = path_buf_read.to_string_lossy().into_owned();
-    helper
-        .synced_read_filestream_to_path(
-            &path_string_read,
-            &["root".into(), "large_file_stream2.bin".into()],
-            0,
-        )
-        .unwrap();
-    println!("read_filestream_to_path2 done");
-    let mut file1 = File::open(tmp_file.path()).unwrap();
-    let mut file2 = File::open(tmp_file_read.path()).unwrap();
-
-    let metadata1 = file1.metadata().unwrap();
-    let metadata2 = file2.metadata().unwrap();
-    println!(
-        "original filesize: {:?} and read size: {:?}",
-        metadata1.len(),
-        metadata2.len()
-    );
-    assert_eq!(
-        metadata1.len(),
-        metadata2.len(),
-        "File sizes 2 do not match"
-    );
-
-    let mut content1 = Vec::new();
-    let mut content2 = Vec::new();
-
-    file1.read_to_end(&mut content1).unwrap();
-    file2.read_to_end(&mut content2).unwrap();
-    assert_eq!(content1, content2);
-
-    //Testing reload
-    println!("read_file_stream_from_path2 checks done. Now testing reload");
-    let reload_helper = &mut PrivateDirectoryHelper::synced_reload(blockstore, cid).unwrap();
-
-    let path = vec!["root".into(), "test_reload".into()];
-    let mut cid = reload_helper.synced_mkdir(&path).unwrap();
-    println!("CID for mkdir test_reload: {:?}", cid);
-
-    for i in 1..=reload_itteration {
-        let path = vec!["root".into(), format!("test_reload_{}", i).into()];
-        cid = reload_helper.synced_mkdir(&path).unwrap();
-        println!("CID for mkdir test_reload_{}: {:?}", i, cid);
-    }
-
-    for i in 1..=reload_itteration {
-        println!(
-            "*******************Starting reload write iteration {}******************",
-            i
-        );
-
-        // Generate first dummy 1MB payload
-        let mut data = generate_dummy_data(1 * 1024 * 1024); // 1MB in bytes
-        rand::thread_rng().fill_bytes(&mut data);
-        let tmp_file = NamedTempFile::new().unwrap();
-        async_std::task::block_on(async {
-            async_std::fs::write(tmp_file.path(), &data).await.unwrap();
-        });
-    }
-
-    let ls_result: Vec<(String, wnfs::common::Metadata)> =
-        reload_helper.synced_ls_files(&["root".into()]).unwrap();
-    println!("ls_reload: {:?}", ls_result);
-    let filenames_from_ls: Vec<String> = ls_result.iter().map(|(name, _)| name.clone()).collect();
-
-    let mut found = true;
-    for i in 1..=reload_itteration {
-        let file_name = format!("file_stream_reload{}.bin", i);
-        if !filenames_from_ls.contains(&file_name) {
-            found = false;
-            break;
-        }
-    }
-
-    assert!(found, "Not all expected files are present in reload");
-    // Generate a dummy 100MB payload
-    let mut data = generate_dummy_data(100 * 1024 * 1024); // 1000MB in bytes
-    rand::thread_rng().fill_bytes(&mut data);
-    let tmp_file_reload = NamedTempFile::new().unwrap();
-    async_std::task::block_on(async {
-        async_std::fs::write(tmp_file_reload.path(), &data)
-            .await
-            .unwrap();
-    });
-    let path_buf: PathBuf = tmp_file_reload.path().to_path_buf();
-    let path_string: String = path_buf.to_string_lossy().into_owned();
-
-    let ls_result = reload_helper.synced_ls_files(&["root".into()]).unwrap();
-    println!("ls_reload: {:?}", ls_result);
-    assert!(ls_result
-        .iter()
-        .any(|item| item.0 == "large_file_stream_reload.bin"));
-
-    let tmp_file_read = NamedTempFile::new().unwrap();
-    let path_buf_read: PathBuf = tmp_file_read.path().to_path_buf();
-    let path_string_read: String = path_buf_read.to_string_lossy().into_owned();
-    reload_helper
-        .synced_read_filestream_to_path(
-            &path_string_read,
-            &["root".into(), "large_file_stream2.bin".into()],
-            0,
-        )
-        .unwrap();
-
-    let mut file1 = File::open(tmp_file.path()).unwrap();
-    let mut file2 = File::open(tmp_file_read.path()).unwrap();
-
-    let metadata1 = file1.metadata().unwrap();
-    let metadata2 = file2.metadata().unwrap();
-    println!(
-        "original filesize before reload: {:?} and read size afte reload: {:?}",
-        metadata1.len(),
-        metadata2.len()
-    );
-    assert_eq!(metadata1.len(), metadata2.len(), "File sizes do not match");
-
-    let mut content1 = Vec::new();
-    let mut content2 = Vec::new();
-
-    file1.read_to_end(&mut content1).unwrap();
-    file2.read_to_end(&mut content2).unwrap();
-    assert_eq!(content1, content2);
-}
-
-#[test]
-fn synced_test_large_file_write_stream_with_reload() {
-    let itteration = 2;
-    let empty_key: Vec<u8> = vec![0; 32];
-
-    let store = KVBlockStore::new(
-        String::from("./tmp/synced_test_large_file_write_stream_with_reload"),
-        CODEC_DAG_CBOR,
-    );
-    let blockstore = &mut FFIFriendlyBlockStore::new(Box::new(store));
-
-    let (helper, access_key, cid) =
-        &mut PrivateDirectoryHelper::synced_init(blockstore, empty_key.to_owned()).unwrap();
-
-    let mut cid = cid.to_owned();
-    println!("cid: {:?}", cid);
-    println!("access_key: {:?}", access_key.to_owned());
-
-    for i in 1..=itteration {
-        let path = vec!["root".into(), format!("test_{}", i).into()];
-        cid = helper.synced_mkdir(&path).unwrap();
-        println!("CID for mkdir test_{}: {:?}", i, cid);
-    }
-
-    for i in 1..=itteration {
-        let reload_helper = &mut PrivateDirectoryHelper::synced_reload(blockstore, cid).unwrap();
-        println!(
-            "*******************Starting write iteration {}******************",
-            i
-        );
-
-        // Generate first dummy 1MB payload
-        let mut data = generate_dummy_data(1 * 1024 * 1024); // 1MB in bytes
-        rand::thread_rng().fill_bytes(&mut data);
-        let tmp_file = NamedTempFile::new().unwrap();
-        async_std::task::block_on(async {
-            async_std::fs::write(tmp_file.path(), &data).await.unwrap();
-        });
-    }
-
-    let reload_helper = &mut PrivateDirectoryHelper::synced_reload(blockstore, cid).unwrap();
-    let ls_result: Vec<(String, wnfs::common::Metadata)> =
-        reload_helper.synced_ls_files(&["root".into()]).unwrap();
-    println!("ls: {:?}", ls_result);
-    let filenames_from_ls: Vec<String> = ls_result.iter().map(|(name, _)| name.clone()).collect();
-
-    let mut found = true;
-    for i in 1..=itteration {
-        let file_name = format!("file_stream{}.bin", i);
-        if !filenames_from_ls.contains(&file_name) {
-            found = false;
-            break;
-        }
-    }
-
-    assert!(found, "Not all expected files are present");
-
-    // Generate a dummy 100MB payload
-    let mut data = generate_dummy_data(100 * 1024 * 1024); // 1000MB in bytes
-    rand::thread_rng().fill_bytes(&mut data);
-    let tmp_file = NamedTempFile::new().unwrap();
-    async_std::task::block_on(async {
-        async_std::fs::write(tmp_file.path(), &data).await.unwrap();
-    });
-    let path_buf: PathBuf = tmp_file.path().to_path_buf();
-    let path_string: String = path_buf.to_string_lossy().into_owned();
-
-    let reload_helper = &mut PrivateDirectoryHelper::synced_reload(blockstore, cid).unwrap();
-    let ls_result = reload_helper.synced_ls_files(&["root".into()]).unwrap();
-    println!("ls: {:?}", ls_result);
-    assert!(ls_result
-        .iter()
-        .any(|item| item.0 == "large_file_stream.bin"));
-
-    let tmp_file_read = NamedTempFile::new().unwrap();
-    let path_buf_read: PathBuf = tmp_file_read.path().to_path_buf();
-    let path_string_read: String = path_buf_read.to_string_lossy().into_owned();
-    let reload_helper = &mut PrivateDirectoryHelper::synced_reload(blockstore, cid).unwrap();
-    reload_helper
-        .synced_read_filestream_to_path(
-            &path_string_read,
-            &["root".into(), "large_file_stream.bin".into()],
-            0,
-        )
-        .unwrap();
-
-    let mut file1 = File::open(tmp_file.path()).unwrap();
-    let mut file2 = File::open(tmp_file_read.path()).unwrap();
-
-    let metadata1 = file1.metadata().unwrap();
-    let metadata2 = file2.metadata().unwrap();
-    println!(
-        "original filesize: {:?} and read size: {:?}",
-        metadata1.len(),
-        metadata2.len()
-    );
-    assert_eq!(metadata1.len(), metadata2.len(), "File sizes do not match");
-
-    let mut content1 = Vec::new();
-    let mut content2 = Vec::new();
-
-    file1.read_to_end(&mut content1).unwrap();
-    file2.read_to_end(&mut content2).unwrap();
-    assert_eq!(content1, content2);
-
-    println!("read_file_stream_from_path checks done");
-
-    // Generate second dummy 60MB payload
-    let mut data = generate_dummy_data(60 * 1024 * 1024); // 1000MB in bytes
-    rand::thread_rng().fill_bytes(&mut data);
-    let tmp_file = NamedTempFile::new().unwrap();
-    async_std::task::block_on(async {
-        async_std::fs::write(tmp_file.path(), &data).await.unwrap();
-    });
-    let path_buf: PathBuf = tmp_file.path().to_path_buf();
-    let path_string: String = path_buf.to_string_lossy().into_owned();
-
-    let reload_helper = &mut PrivateDirectoryHelper::synced_reload(blockstore, cid).unwrap();
-
-    let reload_helper = &mut PrivateDirectoryHelper::synced_reload(blockstore, cid).unwrap();
-    let ls_result = reload_helper.synced_ls_files(&["root".into()]).unwrap();
-    println!("ls: {:?}", ls_result);
-    assert!(ls_result
-        .iter()
-        .any(|item| item.0 == "large_file_stream2.bin"));
-
-    let tmp_file_read = NamedTempFile::new().unwrap();
-    let path_buf_read: PathBuf = tmp_file_read.path().to_path_buf();
-    let path_string_read: String = path_buf_read.to_string_lossy().into_owned();
-    let reload_helper = &mut PrivateDirectoryHelper::synced_reload(blockstore, cid).unwrap();
-    reload_helper
-        .synced_read_filestream_to_path(
-            &path_string_read,
-            &["root".into(), "large_file_stream2.bin".into()],
-            0,
-        )
-        .unwrap();
-    println!("read_filestream_to_path2 done");
-    let mut file1 = File::open(tmp_file.path()).unwrap();
-    let mut file2 = File::open(tmp_file_read.path()).unwrap();
-
-    let metadata1 = file1.metadata().unwrap();
-    let metadata2 = file2.metadata().unwrap();
-    println!(
-        "original filesize: {:?} and read size: {:?}",
-        metadata1.len(),
-        metadata2.len()
-    );
-    assert_eq!(
-        metadata1.len(),
-        metadata2.len(),
-        "File sizes 2 do not match"
-    );
-
-    let mut content1 = Vec::new();
-    let mut content2 = Vec::new();
-
-    file1.read_to_end(&mut content1).unwrap();
-    file2.read_to_end(&mut content2).unwrap();
-    assert_eq!(content1, content2);
-}
-
 #[tokio::test]
 async fn test_large_file_write_stream_with_reload() {
     let itteration = 2;
@@ -822,6 +452,16 @@ async fn test_large_file_write_stream_with_reload() {
         rand::thread_rng().fill_bytes(&mut data);
         let tmp_file = NamedTempFile::new().unwrap();
         async_std::fs::write(tmp_file.path(), &data).await.unwrap();
+        let path_buf: PathBuf = tmp_file.path().to_path_buf();
+        let path_string: String = path_buf.to_string_lossy().into_owned();
+        cid = reload_helper
+            .write_file_stream_from_path(
+                &["root".into(), format!("file_stream{}.bin", i)],
+                &path_string,
+            )
+            .await
+            .unwrap();
+        println!("CID for file_stream{}.bin: {:?}", i, cid);
     }
 
     let reload_helper =
@@ -852,6 +492,13 @@ async fn test_large_file_write_stream_with_reload() {
 
     let path_buf: PathBuf = tmp_file.path().to_path_buf();
     let path_string: String = path_buf.to_string_lossy().into_owned();
+    cid = reload_helper
+        .write_file_stream_from_path(
+            &["root".into(), "large_file_stream.bin".into()],
+            &path_string,
+        )
+        .await
+        .unwrap();
 
     let ls_result = reload_helper.ls_files(&["root".into()]).await.unwrap();
     println!("ls: {:?}", ls_result);
@@ -915,6 +562,17 @@ async fn test_large_file_write_stream_with_reload() {
     let path_buf: PathBuf = tmp_file.path().to_path_buf();
     let path_string: String = path_buf.to_string_lossy().into_owned();
 
+    let reload_helper =
+        &mut PrivateDirectoryHelper::load_with_wnfs_key(blockstore, cid, empty_key.to_owned())
+            .await
+            .unwrap();
+    cid = reload_helper
+        .write_file_stream_from_path(
+            &["root".into(), "large_file_stream2.bin".into()],
+            &path_string,
+        )
+        .await
+        .unwrap();
     let reload_helper =
         &mut PrivateDirectoryHelper::load_with_wnfs_key(blockstore, cid, empty_key.to_owned())
             .await
@@ -964,3 +622,223 @@ async fn test_large_file_write_stream_with_reload() {
     file2.read_to_end(&mut content2).unwrap();
     assert_eq!(content1, content2);
 }
+
+#[tokio::test]
+async fn test_independent_sessions() {
+    let store = MemoryBlockStore::new();
+    let session1 = &mut WnfsSession::new(
+        FFIFriendlyBlockStore::new(Box::new(store.to_owned())),
+        vec![1; 32],
+    );
+    let session2 = &mut WnfsSession::new(
+        FFIFriendlyBlockStore::new(Box::new(store.to_owned())),
+        vec![2; 32],
+    );
+    let (helper1, _, _) = &mut session1.init().await.unwrap();
+    let (helper2, _, _) = &mut session2.init().await.unwrap();
+
+    let cid1 = helper1
+        .write_file(&["root".into(), "one.txt".into()], b"one".to_vec(), 0)
+        .await
+        .unwrap();
+    let cid2 = helper2
+        .write_file(&["root".into(), "two.txt".into()], b"two".to_vec(), 0)
+        .await
+        .unwrap();
+
+    let reloaded1 = &mut PrivateDirectoryHelper::reload(session1, cid1)
+        .await
+        .unwrap();
+    let reloaded2 = &mut PrivateDirectoryHelper::reload(session2, cid2)
+        .await
+        .unwrap();
+    let content1 = reloaded1
+        .read_file(&["root".into(), "one.txt".into()])
+        .await
+        .unwrap();
+    let content2 = reloaded2
+        .read_file(&["root".into(), "two.txt".into()])
+        .await
+        .unwrap();
+    assert_eq!(content1, b"one".to_vec());
+    assert_eq!(content2, b"two".to_vec());
+    assert!(PrivateDirectoryHelper::reload(session2, cid1)
+        .await
+        .is_err());
+
+    // Helpers opened by a session share its exchange keypair instead of deriving their own.
+    assert!(Rc::ptr_eq(
+        &helper1.exchange_keypair,
+        &reloaded1.exchange_keypair
+    ));
+    let reloaded = PrivateDirectoryHelper::reload(&mut reloaded1.session(), cid1)
+        .await
+        .unwrap();
+    assert!(Rc::ptr_eq(
+        &reloaded.exchange_keypair,
+        &reloaded1.exchange_keypair
+    ));
+    assert_eq!(
+        reloaded.exchange_public_key().unwrap(),
+        helper1.exchange_public_key().unwrap()
+    );
+}
+
+#[tokio::test]
+async fn test_typed_errors() {
+    let store = MemoryBlockStore::new();
+    let blockstore = &mut FFIFriendlyBlockStore::new(Box::new(store));
+    let (helper, _, cid) = &mut PrivateDirectoryHelper::init(blockstore, vec![0; 32])
+        .await
+        .unwrap();
+
+    let err = helper
+        .read_file(&["root".into(), "missing.txt".into()])
+        .await
+        .unwrap_err();
+    assert_eq!(err, WnfsUtilsError::NotFound("root/missing.txt".into()));
+    assert_eq!(err.code(), 1);
+
+    let err = helper
+        .rm(&["root".into(), "missing.txt".into()])
+        .await
+        .unwrap_err();
+    assert_eq!(err, WnfsUtilsError::NotFound("root/missing.txt".into()));
+
+    let err = PrivateDirectoryHelper::init(blockstore, vec![])
+        .await
+        .err()
+        .unwrap();
+    assert!(matches!(err, WnfsUtilsError::InvalidKey(_)));
+
+    let err = PrivateDirectoryHelper::load_with_wnfs_key(blockstore, *cid, vec![1; 32])
+        .await
+        .err()
+        .unwrap();
+    assert_eq!(err, WnfsUtilsError::ShareNotFound);
+}
+
+#[tokio::test]
+async fn test_read_file_range() {
+    let store = MemoryBlockStore::new();
+    let blockstore = &mut FFIFriendlyBlockStore::new(Box::new(store));
+    let (helper, _, _) = &mut PrivateDirectoryHelper::init(blockstore, vec![0; 32])
+        .await
+        .unwrap();
+
+    // Spans several content blocks.
+    let data: Vec<u8> = (0..600 * 1024).map(|i| (i % 251) as u8).collect();
+    let path = ["root".to_string(), "range.bin".to_string()];
+    helper.write_file(&path, data.to_owned(), 0).await.unwrap();
+
+    let offset = 300 * 1024 + 17;
+    let range = helper.read_file_range(&path, offset, 1000).await.unwrap();
+    assert_eq!(range, data[offset..offset + 1000].to_vec());
+
+    let range = helper.read_file_range(&path, 0, 10).await.unwrap();
+    assert_eq!(range, data[..10].to_vec());
+
+    // Ranges past the end of the file are truncated.
+    let range = helper
+        .read_file_range(&path, data.len() - 5, 100)
+        .await
+        .unwrap();
+    assert_eq!(range, data[data.len() - 5..].to_vec());
+
+    let range = helper
+        .read_file_range(&path, data.len() + 10, 100)
+        .await
+        .unwrap();
+    assert!(range.is_empty());
+}
+
+#[tokio::test]
+async fn test_append_and_write_at() {
+    let store = MemoryBlockStore::new();
+    let blockstore = &mut FFIFriendlyBlockStore::new(Box::new(store));
+    let (helper, _, _) = &mut PrivateDirectoryHelper::init(blockstore, vec![0; 32])
+        .await
+        .unwrap();
+    let path = ["root".to_string(), "log.txt".to_string()];
+
+    helper
+        .write_file(&path, b"hello".to_vec(), 0)
+        .await
+        .unwrap();
+    helper
+        .append_file(&path, b" world".to_vec(), 0)
+        .await
+        .unwrap();
+    assert_eq!(
+        helper.read_file(&path).await.unwrap(),
+        b"hello world".to_vec()
+    );
+
+    helper
+        .write_at(&path, 6, b"there".to_vec(), 0)
+        .await
+        .unwrap();
+    assert_eq!(
+        helper.read_file(&path).await.unwrap(),
+        b"hello there".to_vec()
+    );
+
+    // Overwrites past the end grow the file, filling any gap with zeros.
+    helper
+        .write_at(&path, 9, b"rein".to_vec(), 0)
+        .await
+        .unwrap();
+    assert_eq!(
+        helper.read_file(&path).await.unwrap(),
+        b"hello therein".to_vec()
+    );
+    helper.write_at(&path, 15, b"!".to_vec(), 0).await.unwrap();
+    assert_eq!(
+        helper.read_file(&path).await.unwrap(),
+        b"hello therein\0\0!".to_vec()
+    );
+
+    // Spans several content blocks.
+    let data: Vec<u8> = (0..600 * 1024).map(|i| (i % 251) as u8).collect();
+    let big_path = ["root".to_string(), "big.bin".to_string()];
+    helper
+        .write_file(&big_path, data.to_owned(), 0)
+        .await
+        .unwrap();
+    let offset = 256 * 1024 - 3;
+    helper
+        .write_at(&big_path, offset, vec![7; 10], 0)
+        .await
+        .unwrap();
+    helper.append_file(&big_path, vec![9; 5], 0).await.unwrap();
+    let mut expected = data.to_owned();
+    expected[offset..offset + 10].copy_from_slice(&[7; 10]);
+    expected.extend_from_slice(&[9; 5]);
+    assert_eq!(helper.read_file(&big_path).await.unwrap(), expected);
+
+    // A gap spanning several content blocks is filled with zeros.
+    let gap_path = ["root".to_string(), "gap.bin".to_string()];
+    helper.write_file(&gap_path, vec![1; 3], 0).await.unwrap();
+    let offset = 3 * 256 * 1024 + 5;
+    helper
+        .write_at(&gap_path, offset, vec![2; 4], 0)
+        .await
+        .unwrap();
+    let mut expected = vec![1; 3];
+    expected.resize(offset, 0);
+    expected.extend_from_slice(&[2; 4]);
+    assert_eq!(helper.read_file(&gap_path).await.unwrap(), expected);
+
+    let err = helper
+        .write_at(&gap_path, usize::MAX, b"x".to_vec(), 0)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, WnfsUtilsError::Other(_)));
+    assert_eq!(helper.read_file(&gap_path).await.unwrap(), expected);
+
+    let err = helper
+        .append_file(&["root".into(), "missing.txt".into()], b"x".to_vec(), 0)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, WnfsUtilsError::NotFound(_)));
+}
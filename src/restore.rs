@@ -0,0 +1,149 @@
+//! Restoring files and directories from an older forest cid of the same drive. Restored
+//! nodes are written as new revisions, so the changes made since stay in the history.
+
+use futures::{StreamExt, TryStreamExt};
+use libipld::Cid;
+use std::io;
+use wnfs::private::{PrivateFile, PrivateNode};
+
+use crate::error::WnfsUtilsError;
+use crate::private_forest::{wnfs_error, PrivateDirectoryHelper};
+use crate::stat::custom_metadata;
+
+//--------------------------------------------------------------------------------------------------
+// Implementations
+//--------------------------------------------------------------------------------------------------
+
+impl<'a> PrivateDirectoryHelper<'a> {
+    /// Makes the whole drive look like it did at `forest_cid`.
+    pub async fn rollback_to(&mut self, forest_cid: Cid) -> Result<Cid, WnfsUtilsError> {
+        self.restore_path_from(forest_cid, &[]).await
+    }
+
+    /// Replaces the file or directory at `path_segments` with its state at `forest_cid`,
+    /// including file contents, modification times and custom metadata. Entries added since
+    /// are removed, while nodes that didn't change are left as they are. The forest is
+    /// committed once at the end.
+    pub async fn restore_path_from(
+        &mut self,
+        forest_cid: Cid,
+        path_segments: &[String],
+    ) -> Result<Cid, WnfsUtilsError> {
+        self.check_writable()?;
+        let snapshot = self
+            .session()
+            .load(forest_cid)
+            .await
+            .map_err(wnfs_error("restore_path_from"))?;
+        snapshot
+            .get_node(path_segments)
+            .await
+            .map_err(wnfs_error("restore_path_from"))?;
+
+        let mut transaction = self.transaction();
+        // Directories are restored before their contents.
+        let mut pending = vec![path_segments.to_vec()];
+        while let Some(path) = pending.pop() {
+            let snapshot_node = snapshot
+                .get_node(&path)
+                .await
+                .map_err(wnfs_error("restore_path_from"))?;
+            let current_node = match transaction.helper().get_node(&path).await {
+                Ok(node) => Some(node),
+                Err(WnfsUtilsError::NotFound(_)) => None,
+                Err(err) => return Err(err),
+            };
+            // Nodes that didn't change since the snapshot are kept, with everything below them.
+            let unchanged = match (&current_node, &snapshot_node) {
+                (Some(PrivateNode::File(current_file)), PrivateNode::File(file)) => {
+                    current_file == file
+                }
+                (Some(PrivateNode::Dir(current_dir)), PrivateNode::Dir(dir)) => current_dir == dir,
+                _ => false,
+            };
+            if unchanged {
+                continue;
+            }
+            match &snapshot_node {
+                PrivateNode::File(file) => {
+                    if let Some(PrivateNode::Dir(_)) = current_node {
+                        transaction.rm(&path).await?;
+                    }
+                    transaction
+                        .helper()
+                        .apply_write_file_from(&path, &snapshot, file)
+                        .await?;
+                }
+                PrivateNode::Dir(dir) => {
+                    let names: Vec<String> = dir.get_entries().cloned().collect();
+                    match current_node {
+                        Some(PrivateNode::Dir(current_dir)) => {
+                            let added: Vec<String> = current_dir
+                                .get_entries()
+                                .filter(|name| !names.contains(name))
+                                .cloned()
+                                .collect();
+                            for name in added {
+                                transaction
+                                    .rm(&[path.to_owned(), vec![name]].concat())
+                                    .await?;
+                            }
+                        }
+                        Some(PrivateNode::File(_)) => {
+                            transaction.rm(&path).await?;
+                            transaction.mkdir(&path).await?;
+                        }
+                        None => transaction.mkdir(&path).await?,
+                    }
+                    for name in names.into_iter().rev() {
+                        pending.push([path.to_owned(), vec![name]].concat());
+                    }
+                    transaction
+                        .helper()
+                        .apply_replace_metadata(&path, custom_metadata(dir.get_metadata()))
+                        .await?;
+                }
+            }
+        }
+        transaction.commit().await
+    }
+
+    // Writes `file` of `source`, a helper for another forest cid of the same drive, to
+    // `path_segments` with its content, modification time and custom metadata.
+    pub(crate) async fn apply_write_file_from(
+        &mut self,
+        path_segments: &[String],
+        source: &PrivateDirectoryHelper<'_>,
+        file: &PrivateFile,
+    ) -> Result<(), WnfsUtilsError> {
+        let modification_time_seconds = file
+            .get_metadata()
+            .get_modified()
+            .map(|modified| modified.timestamp())
+            .unwrap_or_default();
+        let content = Box::pin(
+            file.stream_content(0, &source.forest, &source.store)
+                .map(|block| block.map_err(io::Error::other)),
+        )
+        .into_async_read();
+        self.apply_write_file_stream(path_segments, content, modification_time_seconds)
+            .await?;
+        self.apply_replace_metadata(path_segments, custom_metadata(file.get_metadata()))
+            .await
+    }
+
+    pub async fn rollback_to_async(&mut self, forest_cid: Cid) -> Result<Cid, WnfsUtilsError> {
+        self.rollback_to(forest_cid).await
+    }
+
+    pub async fn restore_path_from_async(
+        &mut self,
+        forest_cid: Cid,
+        path_segments: &[String],
+    ) -> Result<Cid, WnfsUtilsError> {
+        self.restore_path_from(forest_cid, path_segments).await
+    }
+}
+
+#[cfg(test)]
+mod restore_tests;
@@ -0,0 +1,123 @@
+use libipld::Ipld;
+
+use crate::blockstore::FFIFriendlyBlockStore;
+use crate::error::WnfsUtilsError;
+use crate::memorystore::MemoryBlockStore;
+use crate::private_forest::PrivateDirectoryHelper;
+
+#[tokio::test]
+async fn rollback_restores_old_state() {
+    let store = MemoryBlockStore::new();
+    let blockstore = &mut FFIFriendlyBlockStore::new(Box::new(store));
+    let (helper, _, _) = &mut PrivateDirectoryHelper::init(blockstore, vec![0; 32])
+        .await
+        .unwrap();
+    helper
+        .write_file(
+            &["root".into(), "a.txt".into()],
+            b"a1".to_vec(),
+            1_700_000_000,
+        )
+        .await
+        .unwrap();
+    helper
+        .write_file(&["root".into(), "keep.txt".into()], b"k".to_vec(), 0)
+        .await
+        .unwrap();
+    helper
+        .set_metadata(
+            &["root".into(), "a.txt".into()],
+            "tag",
+            Ipld::String("old".into()),
+        )
+        .await
+        .unwrap();
+    let old_cid = helper
+        .write_file(
+            &["root".into(), "dir".into(), "b.txt".into()],
+            b"b1".to_vec(),
+            0,
+        )
+        .await
+        .unwrap();
+
+    helper
+        .write_file(&["root".into(), "a.txt".into()], b"a2".to_vec(), 0)
+        .await
+        .unwrap();
+    helper
+        .set_metadata(
+            &["root".into(), "a.txt".into()],
+            "tag",
+            Ipld::String("new".into()),
+        )
+        .await
+        .unwrap();
+    helper.rm(&["root".into(), "dir".into()]).await.unwrap();
+    let new_cid = helper
+        .write_file(&["root".into(), "c.txt".into()], b"c".to_vec(), 0)
+        .await
+        .unwrap();
+
+    let cid = helper.rollback_to(old_cid).await.unwrap();
+    let helper = &mut PrivateDirectoryHelper::load_with_wnfs_key(blockstore, cid, vec![0; 32])
+        .await
+        .unwrap();
+    let content = helper
+        .read_file(&["root".into(), "a.txt".into()])
+        .await
+        .unwrap();
+    assert_eq!(content, b"a1".to_vec());
+    let stat = helper.stat(&["root".into(), "a.txt".into()]).await.unwrap();
+    assert_eq!(stat.modified, Some(1_700_000_000));
+    assert_eq!(stat.metadata.get("tag"), Some(&Ipld::String("old".into())));
+    let content = helper
+        .read_file(&["root".into(), "dir".into(), "b.txt".into()])
+        .await
+        .unwrap();
+    assert_eq!(content, b"b1".to_vec());
+    assert!(!helper
+        .exists(&["root".into(), "c.txt".into()])
+        .await
+        .unwrap());
+
+    // Unchanged files aren't written again.
+    let revisions = helper
+        .history(&["root".into(), "keep.txt".into()], 10)
+        .await
+        .unwrap();
+    assert_eq!(revisions.len(), 1);
+
+    // Later changes are kept in the history.
+    let revisions = helper
+        .history(&["root".into(), "a.txt".into()], 2)
+        .await
+        .unwrap();
+    let content = helper
+        .read_file_at_revision(&["root".into(), "a.txt".into()], &revisions[1].revision)
+        .await
+        .unwrap();
+    assert_eq!(content, b"a2".to_vec());
+
+    // A single path can be restored from a newer snapshot too.
+    helper
+        .restore_path_from(new_cid, &["root".into(), "c.txt".into()])
+        .await
+        .unwrap();
+    let content = helper
+        .read_file(&["root".into(), "c.txt".into()])
+        .await
+        .unwrap();
+    assert_eq!(content, b"c".to_vec());
+    let content = helper
+        .read_file(&["root".into(), "a.txt".into()])
+        .await
+        .unwrap();
+    assert_eq!(content, b"a1".to_vec());
+
+    let err = helper
+        .restore_path_from(old_cid, &["root".into(), "c.txt".into()])
+        .await
+        .unwrap_err();
+    assert!(matches!(err, WnfsUtilsError::NotFound(_)));
+}
@@ -0,0 +1,481 @@
+//! Sharing private directories with other users. A share is the access key of the shared
+//! directory, encrypted for the recipient's exchange key and stored in the forest under a
+//! label derived from the sharer's DID, the recipient's exchange key and a counter.
+//! Shares are also listed in a registry in the drive state, outside the user's files, so
+//! they can be revoked later.
+//! Recipients open a shared directory as a helper of its own.
+
+use libipld::Cid;
+use rand::{thread_rng, RngCore};
+use serde::{Deserialize, Serialize};
+use sha3::{Digest, Sha3_256};
+use std::collections::{BTreeMap, BTreeSet};
+use std::rc::Rc;
+use wnfs::private::{
+    share::{recipient, sharer},
+    PrivateNode,
+};
+
+use crate::blockstore::FFIFriendlyBlockStore;
+use crate::error::WnfsUtilsError;
+use crate::private_forest::{
+    grant_write_access, share_access_key, wnfs_error, write_grant_did, PrivateDirectoryHelper,
+    SeededExchangeKey,
+};
+
+// Directory of the drive state holding the share registry.
+const SHARES_DIR: &str = "shares";
+
+const SHARE_REGISTRY: &str = "registry.json";
+
+// Where a received share is found, so that a share handle opens other forest cids through
+// the same share instead of the recipient's own drive.
+#[derive(Debug, Clone)]
+pub(crate) struct ShareOrigin {
+    pub(crate) sharer_did: String,
+    pub(crate) counter: ShareCounter,
+    pub(crate) writable: bool,
+}
+
+// Which share of a sharer a handle is opened through.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum ShareCounter {
+    // The share under this counter.
+    At(u64),
+    // The latest share from this counter on. Revoking shares the directory again with the
+    // remaining recipients under new counters, which handles opened this way follow.
+    LatestFrom(u64),
+}
+
+/// A share of a directory with another user, as listed in the share registry.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ShareRecord {
+    pub path_segments: Vec<String>,
+    pub recipient_did: String,
+    /// Modulus of the recipient's RSA exchange key.
+    pub recipient_exchange_public_key: Vec<u8>,
+    /// Share counter the recipient needs, together with the sharer's DID, to find the share.
+    pub counter: u64,
+    /// Whether the recipient was granted to open the share writable.
+    #[serde(default)]
+    pub writable: bool,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Implementations
+//--------------------------------------------------------------------------------------------------
+
+impl<'a> PrivateDirectoryHelper<'a> {
+    /// The DID of this drive's owner, which recipients need to find shares from this drive.
+    /// It is derived from the public exchange key, so it can be handed out freely.
+    pub fn did(&self) -> Result<String, WnfsUtilsError> {
+        Ok(did_of(&self.exchange_public_key()?))
+    }
+
+    /// The public exchange key derived from the wnfs key, to hand to users that want to share
+    /// with this drive's owner.
+    pub fn exchange_public_key(&self) -> Result<Vec<u8>, WnfsUtilsError> {
+        Ok(self.exchange_keypair.encode_public_key())
+    }
+
+    /// Shares the directory at `path_segments` with the owner of
+    /// `recipient_exchange_public_key`. The recipient can read the shared directory and its
+    /// later revisions, and if `writable` is set, open it writable. Returns the share counter
+    /// to hand to the recipient and the new forest cid. Files can't be shared on their own and
+    /// fail with `NotADirectory`, as recipients open a share as a helper rooted at the shared
+    /// directory.
+    pub async fn share_path(
+        &mut self,
+        path_segments: &[String],
+        recipient_exchange_public_key: &[u8],
+        recipient_did: &str,
+        writable: bool,
+    ) -> Result<(u64, Cid), WnfsUtilsError> {
+        self.check_writable()?;
+        let node = self
+            .get_node(path_segments)
+            .await
+            .map_err(wnfs_error("share_path"))?;
+        if !matches!(node, PrivateNode::Dir(_)) {
+            return Err(WnfsUtilsError::NotADirectory);
+        }
+        let access_key = node
+            .store(&mut self.forest, &mut self.store, &mut self.rng)
+            .await
+            .map_err(wnfs_error("share_path"))?;
+        let counter = share_access_key(
+            &access_key,
+            recipient_exchange_public_key,
+            &self.did()?,
+            &mut self.forest,
+            &mut self.store,
+        )
+        .await
+        .map_err(wnfs_error("share_path"))?;
+        if writable {
+            grant_write_access(
+                &access_key,
+                counter,
+                recipient_exchange_public_key,
+                &self.did()?,
+                &mut self.forest,
+                &mut self.store,
+            )
+            .await
+            .map_err(wnfs_error("share_path"))?;
+        }
+
+        let mut shares = self.list_shares().await?;
+        shares.push(ShareRecord {
+            path_segments: path_segments.to_vec(),
+            recipient_did: recipient_did.to_owned(),
+            recipient_exchange_public_key: recipient_exchange_public_key.to_vec(),
+            counter,
+            writable,
+        });
+        self.apply_write_share_registry(&shares).await?;
+        let cid = self.store_root_dir("share_path").await?;
+        Ok((counter, cid))
+    }
+
+    /// Revokes all shares of the directory at `path_segments`. See `revoke_recipient`.
+    pub async fn revoke(&mut self, path_segments: &[String]) -> Result<Cid, WnfsUtilsError> {
+        self.revoke_shares(path_segments, None).await
+    }
+
+    /// Revokes the shares of the directory at `path_segments` with `recipient_did`.
+    /// The node is moved to fresh keys and a fresh ratchet, so revoked access keys can't
+    /// decrypt its new revisions, while older revisions stay readable to them. The remaining
+    /// shares in the registry of the node and of everything below it are shared again with
+    /// the new keys. Shares of directories above the node still include it.
+    pub async fn revoke_recipient(
+        &mut self,
+        path_segments: &[String],
+        recipient_did: &str,
+    ) -> Result<Cid, WnfsUtilsError> {
+        self.revoke_shares(path_segments, Some(recipient_did)).await
+    }
+
+    async fn revoke_shares(
+        &mut self,
+        path_segments: &[String],
+        recipient_did: Option<&str>,
+    ) -> Result<Cid, WnfsUtilsError> {
+        self.check_writable()?;
+        let Some((name, parent)) = path_segments.split_last() else {
+            return Err(WnfsUtilsError::InvalidPath);
+        };
+        let (revoked, remaining): (Vec<ShareRecord>, Vec<ShareRecord>) =
+            self.list_shares().await?.into_iter().partition(|share| {
+                share.path_segments == path_segments
+                    && recipient_did.is_none_or(|did| share.recipient_did == did)
+            });
+        if revoked.is_empty() {
+            return Err(WnfsUtilsError::ShareNotFound);
+        }
+
+        let mut id = [0u8; 16];
+        self.rng.fill_bytes(&mut id);
+        let did = self.did()?;
+        let mut transaction = self.transaction();
+        // Moving a node gives it and everything below it fresh keys and a fresh ratchet.
+        let moved_path_segments = [
+            parent.to_vec(),
+            vec![format!(
+                "{}.{}",
+                name,
+                PrivateDirectoryHelper::bytes_to_hex_str(&id)
+            )],
+        ]
+        .concat();
+        transaction.mv(path_segments, &moved_path_segments).await?;
+        transaction.mv(&moved_path_segments, path_segments).await?;
+
+        let helper = transaction.helper();
+        let mut shares = Vec::new();
+        for mut share in remaining {
+            if share.path_segments.starts_with(path_segments) {
+                let node = helper
+                    .get_node(&share.path_segments)
+                    .await
+                    .map_err(wnfs_error("revoke"))?;
+                let access_key = node
+                    .store(&mut helper.forest, &mut helper.store, &mut helper.rng)
+                    .await
+                    .map_err(wnfs_error("revoke"))?;
+                share.counter = share_access_key(
+                    &access_key,
+                    &share.recipient_exchange_public_key,
+                    &did,
+                    &mut helper.forest,
+                    &mut helper.store,
+                )
+                .await
+                .map_err(wnfs_error("revoke"))?;
+                if share.writable {
+                    grant_write_access(
+                        &access_key,
+                        share.counter,
+                        &share.recipient_exchange_public_key,
+                        &did,
+                        &mut helper.forest,
+                        &mut helper.store,
+                    )
+                    .await
+                    .map_err(wnfs_error("revoke"))?;
+                }
+            }
+            shares.push(share);
+        }
+        helper.apply_write_share_registry(&shares).await?;
+        transaction.commit().await
+    }
+
+    /// Opens the directory shared with the owner of `wnfs_key` by `sharer_did` in the forest at
+    /// `forest_cid`, under share `counter` or the latest share if `None`. The returned helper
+    /// works on the shared directory as its root directory. Unless `writable` is set, every
+    /// change fails with `ReadOnly`. Opening a share writable fails with `ReadOnly` unless the
+    /// sharer granted it with `share_path`. Operations that open other forest cids, like
+    /// `diff`, `restore_path_from` or `merge`, open them through the same share. Handles of
+    /// the latest share look it up again there, so they keep working after a revoke shared the
+    /// directory again under a new counter.
+    pub async fn receive_share(
+        store: &mut FFIFriendlyBlockStore<'a>,
+        sharer_did: &str,
+        counter: Option<u64>,
+        forest_cid: Cid,
+        wnfs_key: Vec<u8>,
+        writable: bool,
+    ) -> Result<PrivateDirectoryHelper<'a>, WnfsUtilsError> {
+        let exchange_keypair =
+            Self::derive_exchange_keypair(&wnfs_key).map_err(wnfs_error("receive_share"))?;
+        Self::receive_share_with_keypair(
+            store,
+            sharer_did,
+            counter.map_or(ShareCounter::LatestFrom(0), ShareCounter::At),
+            forest_cid,
+            wnfs_key,
+            exchange_keypair,
+            writable,
+        )
+        .await
+    }
+
+    pub(crate) async fn receive_share_with_keypair(
+        store: &mut FFIFriendlyBlockStore<'a>,
+        sharer_did: &str,
+        counter: ShareCounter,
+        forest_cid: Cid,
+        wnfs_key: Vec<u8>,
+        exchange_keypair: Rc<SeededExchangeKey>,
+        writable: bool,
+    ) -> Result<PrivateDirectoryHelper<'a>, WnfsUtilsError> {
+        let exchange_public_key = exchange_keypair.encode_public_key();
+        let forest = &mut Self::load_private_forest(store.to_owned(), forest_cid).await?;
+
+        let (counter, origin_counter) = match counter {
+            ShareCounter::At(counter) => (counter, ShareCounter::At(counter)),
+            ShareCounter::LatestFrom(start) => {
+                let counter = recipient::find_latest_share_counter(
+                    start,
+                    1000,
+                    &exchange_public_key,
+                    sharer_did,
+                    forest,
+                    store,
+                )
+                .await
+                .map_err(wnfs_error("receive_share"))?
+                .ok_or(WnfsUtilsError::ShareNotFound)?;
+                (counter, ShareCounter::LatestFrom(counter))
+            }
+        };
+        let name = sharer::create_share_name(counter, sharer_did, &exchange_public_key, forest);
+        let node = recipient::receive_share(&name, exchange_keypair.as_ref(), forest, store)
+            .await
+            .map_err(wnfs_error("receive_share"))?;
+        if writable {
+            let grant_name = sharer::create_share_name(
+                counter,
+                &write_grant_did(sharer_did),
+                &exchange_public_key,
+                forest,
+            );
+            recipient::receive_share(&grant_name, exchange_keypair.as_ref(), forest, store)
+                .await
+                .map_err(|_| WnfsUtilsError::ReadOnly)?;
+        }
+        let first_root_dir = node.as_dir().map_err(wnfs_error("receive_share"))?;
+        let root_dir = node
+            .search_latest(forest, store)
+            .await
+            .map_err(wnfs_error("receive_share"))?
+            .as_dir()
+            .map_err(wnfs_error("receive_share"))?;
+        Ok(Self {
+            store: store.to_owned(),
+            forest: forest.to_owned(),
+            root_dir,
+            first_root_dir,
+            state_dir: None,
+            rng: thread_rng(),
+            quota: None,
+            read_only: !writable,
+            share: Some(ShareOrigin {
+                sharer_did: sharer_did.to_owned(),
+                counter: origin_counter,
+                writable,
+            }),
+            wnfs_key,
+            exchange_keypair,
+        })
+    }
+
+    /// Whether this helper is a read-only share handle, which fails to change anything.
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    // Fails with `ReadOnly` on read-only share handles. Every mutation checks this first,
+    // so that nothing is written to the forest or the block store.
+    pub(crate) fn check_writable(&self) -> Result<(), WnfsUtilsError> {
+        if self.read_only {
+            return Err(WnfsUtilsError::ReadOnly);
+        }
+        Ok(())
+    }
+
+    /// Lists the shares in the share registry of the drive.
+    pub async fn list_shares(&mut self) -> Result<Vec<ShareRecord>, WnfsUtilsError> {
+        self.read_share_registry().await
+    }
+
+    pub(crate) async fn read_share_registry(&self) -> Result<Vec<ShareRecord>, WnfsUtilsError> {
+        let Some(registry) = self.read_state_file(&share_registry_path()).await? else {
+            return Ok(Vec::new());
+        };
+        serde_json::from_slice(&registry)
+            .map_err(|e| WnfsUtilsError::Other(e.to_string()))
+            .map_err(wnfs_error("list_shares"))
+    }
+
+    // Merges the share registries of both sides of a merge by path and recipient. A share
+    // made on either side since `base` is kept with the highest counter of both sides, and
+    // one revoked on either side stays revoked.
+    pub(crate) async fn apply_merge_share_registry(
+        &mut self,
+        base: &PrivateDirectoryHelper<'_>,
+        ours: &PrivateDirectoryHelper<'_>,
+        theirs: &PrivateDirectoryHelper<'_>,
+    ) -> Result<(), WnfsUtilsError> {
+        let base_keys: BTreeSet<_> = base
+            .read_share_registry()
+            .await?
+            .iter()
+            .map(share_key)
+            .collect();
+        let ours = ours.read_share_registry().await?;
+        let theirs = theirs.read_share_registry().await?;
+        let ours_keys: BTreeSet<_> = ours.iter().map(share_key).collect();
+        let theirs_keys: BTreeSet<_> = theirs.iter().map(share_key).collect();
+        let mut shares = BTreeMap::new();
+        for share in ours.into_iter().chain(theirs) {
+            let key = share_key(&share);
+            let revoked = base_keys.contains(&key)
+                && !(ours_keys.contains(&key) && theirs_keys.contains(&key));
+            let superseded = shares
+                .get(&key)
+                .is_some_and(|kept: &ShareRecord| kept.counter >= share.counter);
+            if !revoked && !superseded {
+                shares.insert(key, share);
+            }
+        }
+        let shares: Vec<ShareRecord> = shares.into_values().collect();
+        if shares != self.read_share_registry().await? {
+            self.apply_write_share_registry(&shares).await?;
+        }
+        Ok(())
+    }
+
+    pub(crate) async fn apply_write_share_registry(
+        &mut self,
+        shares: &[ShareRecord],
+    ) -> Result<(), WnfsUtilsError> {
+        let registry = serde_json::to_vec(shares)
+            .map_err(|e| WnfsUtilsError::Other(e.to_string()))
+            .map_err(wnfs_error("write_share_registry"))?;
+        self.apply_write_state_file(&share_registry_path(), registry)
+            .await
+    }
+
+    pub async fn share_path_async(
+        &mut self,
+        path_segments: &[String],
+        recipient_exchange_public_key: &[u8],
+        recipient_did: &str,
+        writable: bool,
+    ) -> Result<(u64, Cid), WnfsUtilsError> {
+        self.share_path(
+            path_segments,
+            recipient_exchange_public_key,
+            recipient_did,
+            writable,
+        )
+        .await
+    }
+
+    pub async fn revoke_async(&mut self, path_segments: &[String]) -> Result<Cid, WnfsUtilsError> {
+        self.revoke(path_segments).await
+    }
+
+    pub async fn revoke_recipient_async(
+        &mut self,
+        path_segments: &[String],
+        recipient_did: &str,
+    ) -> Result<Cid, WnfsUtilsError> {
+        self.revoke_recipient(path_segments, recipient_did).await
+    }
+
+    pub async fn receive_share_async(
+        store: &mut FFIFriendlyBlockStore<'a>,
+        sharer_did: &str,
+        counter: Option<u64>,
+        forest_cid: Cid,
+        wnfs_key: Vec<u8>,
+        writable: bool,
+    ) -> Result<PrivateDirectoryHelper<'a>, WnfsUtilsError> {
+        PrivateDirectoryHelper::receive_share(
+            store, sharer_did, counter, forest_cid, wnfs_key, writable,
+        )
+        .await
+    }
+
+    pub async fn list_shares_async(&mut self) -> Result<Vec<ShareRecord>, WnfsUtilsError> {
+        self.list_shares().await
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Functions
+//--------------------------------------------------------------------------------------------------
+
+// DID of the owner of `exchange_public_key`, the hex of its SHA3-256 hash. Unlike the label
+// the drive shares its root directory with itself under, it reveals nothing about the wnfs key.
+fn did_of(exchange_public_key: &[u8]) -> String {
+    PrivateDirectoryHelper::bytes_to_hex_str(&Sha3_256::digest(exchange_public_key))
+}
+
+// Identifies a share in the registry: the shared path and the recipient.
+fn share_key(share: &ShareRecord) -> (Vec<String>, String) {
+    (
+        share.path_segments.to_owned(),
+        share.recipient_did.to_owned(),
+    )
+}
+
+fn share_registry_path() -> Vec<String> {
+    vec![SHARES_DIR.into(), SHARE_REGISTRY.into()]
+}
+
+#[cfg(test)]
+mod share_tests;
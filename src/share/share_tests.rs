@@ -0,0 +1,343 @@
+use wnfs::private::share::recipient;
+
+use crate::blockstore::FFIFriendlyBlockStore;
+use crate::diff::{Change, ChangeKind};
+use crate::error::WnfsUtilsError;
+use crate::memorystore::MemoryBlockStore;
+use crate::private_forest::{PrivateDirectoryHelper, WnfsSession};
+use crate::share::ShareRecord;
+use crate::stat::NodeKind;
+
+#[tokio::test]
+async fn share_path_stores_share_for_recipient() {
+    let store = MemoryBlockStore::new();
+    let blockstore = &mut FFIFriendlyBlockStore::new(Box::new(store));
+    let (recipient_helper, _, _) = &mut PrivateDirectoryHelper::init(blockstore, vec![1; 32])
+        .await
+        .unwrap();
+    let recipient_key = recipient_helper.exchange_public_key().unwrap();
+    let recipient_did = recipient_helper.did().unwrap();
+
+    let (helper, _, _) = &mut PrivateDirectoryHelper::init(blockstore, vec![0; 32])
+        .await
+        .unwrap();
+    helper
+        .write_file(
+            &["photos".into(), "trips".into(), "a.jpg".into()],
+            b"a".to_vec(),
+            0,
+        )
+        .await
+        .unwrap();
+    let (counter, _) = helper
+        .share_path(&["photos".into()], &recipient_key, &recipient_did, false)
+        .await
+        .unwrap();
+    assert_eq!(counter, 0);
+    let (counter, cid) = helper
+        .share_path(
+            &["photos".into(), "trips".into()],
+            &recipient_key,
+            &recipient_did,
+            true,
+        )
+        .await
+        .unwrap();
+    assert_eq!(counter, 1);
+    // Recipients open shares as directories, so files can't be shared on their own.
+    let err = helper
+        .share_path(
+            &["photos".into(), "trips".into(), "a.jpg".into()],
+            &recipient_key,
+            &recipient_did,
+            false,
+        )
+        .await
+        .unwrap_err();
+    assert_eq!(err, WnfsUtilsError::NotADirectory);
+
+    let helper = &mut PrivateDirectoryHelper::load_with_wnfs_key(blockstore, cid, vec![0; 32])
+        .await
+        .unwrap();
+    let latest_counter = recipient::find_latest_share_counter(
+        0,
+        1000,
+        &recipient_key,
+        &helper.did().unwrap(),
+        &helper.forest,
+        &helper.store,
+    )
+    .await
+    .unwrap();
+    assert_eq!(latest_counter, Some(1));
+    // The share registry is kept out of the user's files.
+    let names: Vec<String> = helper
+        .ls_files(&[])
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|(name, _)| name)
+        .collect();
+    assert_eq!(names, vec!["photos".to_string()]);
+    assert_eq!(
+        helper.list_shares().await.unwrap(),
+        vec![
+            ShareRecord {
+                path_segments: vec!["photos".into()],
+                recipient_did: recipient_did.to_owned(),
+                recipient_exchange_public_key: recipient_key.to_owned(),
+                counter: 0,
+                writable: false,
+            },
+            ShareRecord {
+                path_segments: vec!["photos".into(), "trips".into()],
+                recipient_did,
+                recipient_exchange_public_key: recipient_key,
+                counter: 1,
+                writable: true,
+            },
+        ]
+    );
+}
+
+#[tokio::test]
+async fn share_path_of_missing_path_fails() {
+    let store = MemoryBlockStore::new();
+    let blockstore = &mut FFIFriendlyBlockStore::new(Box::new(store));
+    let (helper, _, _) = &mut PrivateDirectoryHelper::init(blockstore, vec![0; 32])
+        .await
+        .unwrap();
+    let key = helper.exchange_public_key().unwrap();
+    assert!(helper
+        .share_path(&["missing".into()], &key, "did:key:someone", false)
+        .await
+        .is_err());
+    assert!(helper.list_shares().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn receive_share_opens_shared_directory() {
+    let store = MemoryBlockStore::new();
+    let blockstore = &mut FFIFriendlyBlockStore::new(Box::new(store.clone()));
+    let recipient_session = &mut WnfsSession::new(blockstore.to_owned(), vec![1; 32]);
+    let (recipient_helper, _, _) = &mut recipient_session.init().await.unwrap();
+    let recipient_key = recipient_helper.exchange_public_key().unwrap();
+
+    let (helper, _, _) = &mut PrivateDirectoryHelper::init(blockstore, vec![0; 32])
+        .await
+        .unwrap();
+    helper
+        .write_file(&["photos".into(), "a.jpg".into()], b"a".to_vec(), 0)
+        .await
+        .unwrap();
+    // Share 0 is read-only, share 1 may be opened writable.
+    for writable in [false, true] {
+        helper
+            .share_path(
+                &["photos".into()],
+                &recipient_key,
+                &recipient_helper.did().unwrap(),
+                writable,
+            )
+            .await
+            .unwrap();
+    }
+    // Later revisions of the shared directory are visible to the recipient as well.
+    let cid = helper
+        .write_file(&["photos".into(), "b.jpg".into()], b"b".to_vec(), 0)
+        .await
+        .unwrap();
+
+    let shared = &mut recipient_session
+        .receive_share(&helper.did().unwrap(), cid, false)
+        .await
+        .unwrap();
+    assert!(shared.is_read_only());
+    assert_eq!(shared.read_file(&["a.jpg".into()]).await.unwrap(), b"a");
+    assert_eq!(shared.read_file(&["b.jpg".into()]).await.unwrap(), b"b");
+    // Read-only handles fail before writing any blocks.
+    let block_count = store.len();
+    let err = shared
+        .write_file(&["c.jpg".into()], b"c".to_vec(), 0)
+        .await
+        .unwrap_err();
+    assert_eq!(err, WnfsUtilsError::ReadOnly);
+    assert!(!shared.exists(&["c.jpg".into()]).await.unwrap());
+    assert_eq!(store.len(), block_count);
+
+    // Opening a share writable needs the sharer's grant.
+    let err = recipient_session
+        .receive_share_at(&helper.did().unwrap(), 0, cid, true)
+        .await
+        .unwrap_err();
+    assert_eq!(err, WnfsUtilsError::ReadOnly);
+    let shared = &mut recipient_session
+        .receive_share_at(&helper.did().unwrap(), 1, cid, true)
+        .await
+        .unwrap();
+    assert!(!shared.is_read_only());
+    let shared_cid = shared
+        .write_file(&["c.jpg".into()], b"c".to_vec(), 0)
+        .await
+        .unwrap();
+    assert_eq!(shared.read_file(&["c.jpg".into()]).await.unwrap(), b"c");
+    // Other forest cids are opened through the same share.
+    assert_eq!(
+        shared.diff(cid, shared_cid, &[]).await.unwrap(),
+        vec![Change {
+            path_segments: vec!["c.jpg".into()],
+            change: ChangeKind::Added,
+            kind: NodeKind::File,
+        }]
+    );
+    shared.rollback_to(cid).await.unwrap();
+    assert!(!shared.exists(&["c.jpg".into()]).await.unwrap());
+    assert_eq!(shared.read_file(&["a.jpg".into()]).await.unwrap(), b"a");
+
+    let err = recipient_session
+        .receive_share(&"02".repeat(32), cid, false)
+        .await
+        .unwrap_err();
+    assert_eq!(err, WnfsUtilsError::ShareNotFound);
+}
+
+#[tokio::test]
+async fn revoked_recipient_loses_new_revisions() {
+    let store = MemoryBlockStore::new();
+    let blockstore = &mut FFIFriendlyBlockStore::new(Box::new(store));
+    let revoked_session = &mut WnfsSession::new(blockstore.to_owned(), vec![1; 32]);
+    let (revoked_helper, _, _) = &mut revoked_session.init().await.unwrap();
+    let remaining_session = &mut WnfsSession::new(blockstore.to_owned(), vec![2; 32]);
+    let (remaining_helper, _, _) = &mut remaining_session.init().await.unwrap();
+
+    let (helper, _, _) = &mut PrivateDirectoryHelper::init(blockstore, vec![0; 32])
+        .await
+        .unwrap();
+    helper
+        .write_file(&["photos".into(), "a.jpg".into()], b"a".to_vec(), 0)
+        .await
+        .unwrap();
+    for recipient_helper in [&*revoked_helper, &*remaining_helper] {
+        helper
+            .share_path(
+                &["photos".into()],
+                &recipient_helper.exchange_public_key().unwrap(),
+                &recipient_helper.did().unwrap(),
+                false,
+            )
+            .await
+            .unwrap();
+    }
+
+    helper
+        .revoke_recipient(&["photos".into()], &revoked_helper.did().unwrap())
+        .await
+        .unwrap();
+    let cid = helper
+        .write_file(&["photos".into(), "b.jpg".into()], b"b".to_vec(), 0)
+        .await
+        .unwrap();
+    assert_eq!(
+        helper
+            .read_file(&["photos".into(), "a.jpg".into()])
+            .await
+            .unwrap(),
+        b"a"
+    );
+
+    // The revoked share still opens the revision it was made for, but no later ones.
+    let revoked = &mut revoked_session
+        .receive_share(&helper.did().unwrap(), cid, false)
+        .await
+        .unwrap();
+    assert!(revoked.exists(&["a.jpg".into()]).await.unwrap());
+    assert!(!revoked.exists(&["b.jpg".into()]).await.unwrap());
+
+    let remaining = &mut remaining_session
+        .receive_share(&helper.did().unwrap(), cid, false)
+        .await
+        .unwrap();
+    assert_eq!(remaining.read_file(&["b.jpg".into()]).await.unwrap(), b"b");
+
+    let shares = helper.list_shares().await.unwrap();
+    assert_eq!(shares.len(), 1);
+    assert_eq!(shares[0].recipient_did, remaining_helper.did().unwrap());
+    assert_eq!(shares[0].counter, 1);
+
+    let err = helper.revoke(&["missing".into()]).await.unwrap_err();
+    assert_eq!(err, WnfsUtilsError::ShareNotFound);
+}
+
+#[tokio::test]
+async fn share_handles_follow_shares_made_by_revoke() {
+    let store = MemoryBlockStore::new();
+    let blockstore = &mut FFIFriendlyBlockStore::new(Box::new(store));
+    let revoked_session = &mut WnfsSession::new(blockstore.to_owned(), vec![1; 32]);
+    let (revoked_helper, _, _) = &mut revoked_session.init().await.unwrap();
+    let remaining_session = &mut WnfsSession::new(blockstore.to_owned(), vec![2; 32]);
+    let (remaining_helper, _, _) = &mut remaining_session.init().await.unwrap();
+
+    let (helper, _, _) = &mut PrivateDirectoryHelper::init(blockstore, vec![0; 32])
+        .await
+        .unwrap();
+    helper
+        .write_file(&["photos".into(), "a.jpg".into()], b"a".to_vec(), 0)
+        .await
+        .unwrap();
+    let mut cid = None;
+    for recipient_helper in [&*revoked_helper, &*remaining_helper] {
+        let (_, shared_cid) = helper
+            .share_path(
+                &["photos".into()],
+                &recipient_helper.exchange_public_key().unwrap(),
+                &recipient_helper.did().unwrap(),
+                false,
+            )
+            .await
+            .unwrap();
+        cid = Some(shared_cid);
+    }
+    let remaining = remaining_session
+        .receive_share(&helper.did().unwrap(), cid.unwrap(), false)
+        .await
+        .unwrap();
+    let pinned = remaining_session
+        .receive_share_at(&helper.did().unwrap(), 0, cid.unwrap(), false)
+        .await
+        .unwrap();
+
+    helper
+        .revoke_recipient(&["photos".into()], &revoked_helper.did().unwrap())
+        .await
+        .unwrap();
+    let cid = helper
+        .write_file(&["photos".into(), "b.jpg".into()], b"b".to_vec(), 0)
+        .await
+        .unwrap();
+
+    // The handle opened before the revoke finds the new share on reload.
+    let reloaded = &mut remaining.session().load(cid).await.unwrap();
+    assert_eq!(reloaded.read_file(&["b.jpg".into()]).await.unwrap(), b"b");
+    let reloaded = &mut reloaded.session().load(cid).await.unwrap();
+    assert_eq!(reloaded.read_file(&["b.jpg".into()]).await.unwrap(), b"b");
+    // A handle of a given counter keeps opening that share.
+    let reloaded = &mut pinned.session().load(cid).await.unwrap();
+    assert!(!reloaded.exists(&["b.jpg".into()]).await.unwrap());
+}
+
+#[tokio::test]
+async fn did_does_not_reveal_wnfs_key() {
+    let store = MemoryBlockStore::new();
+    let blockstore = &mut FFIFriendlyBlockStore::new(Box::new(store));
+    let wnfs_key = vec![7; 32];
+    let (helper, _, cid) = &mut PrivateDirectoryHelper::init(blockstore, wnfs_key.to_owned())
+        .await
+        .unwrap();
+    let did = helper.did().unwrap();
+    assert!(!did.contains(&PrivateDirectoryHelper::bytes_to_hex_str(&wnfs_key)));
+
+    let helper = PrivateDirectoryHelper::load_with_wnfs_key(blockstore, *cid, wnfs_key)
+        .await
+        .unwrap();
+    assert_eq!(helper.did().unwrap(), did);
+}
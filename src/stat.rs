@@ -0,0 +1,228 @@
+//! Metadata of files and directories and detailed directory listings, looked up directly in
+//! the private tree.
+
+use futures::{pin_mut, StreamExt};
+use libipld::Ipld;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use wnfs::{
+    common::Metadata,
+    private::{PrivateFile, PrivateNode, MAX_BLOCK_CONTENT_SIZE},
+};
+
+use crate::error::WnfsUtilsError;
+use crate::private_forest::{wnfs_error, PrivateDirectoryHelper};
+
+// Metadata keys maintained by wnfs itself rather than by users.
+pub(crate) const WNFS_METADATA_KEYS: [&str; 2] = ["created", "modified"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NodeKind {
+    File,
+    Dir,
+}
+
+/// Result of `PrivateDirectoryHelper::stat`. Times are in seconds since the unix epoch.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NodeStat {
+    pub kind: NodeKind,
+    /// Size of the file content in bytes, `None` for directories.
+    pub size: Option<u64>,
+    pub created: Option<i64>,
+    pub modified: Option<i64>,
+    /// Number of encrypted blocks the file content is split into, 0 for directories.
+    pub content_block_count: u64,
+    /// Metadata entries other than the times maintained by wnfs.
+    pub metadata: BTreeMap<String, Ipld>,
+}
+
+/// Entry of a directory listing. Times are in seconds since the unix epoch.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DirEntry {
+    pub name: String,
+    pub kind: NodeKind,
+    /// Size of the file content in bytes, `None` for directories.
+    pub size: Option<u64>,
+    pub modified: Option<i64>,
+    pub created: Option<i64>,
+}
+
+/// A page of `PrivateDirectoryHelper::ls_detailed`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DirListing {
+    pub entries: Vec<DirEntry>,
+    /// Cursor for the next page, `None` if this is the last page.
+    pub cursor: Option<String>,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Implementations
+//--------------------------------------------------------------------------------------------------
+
+impl NodeStat {
+    fn new(
+        kind: NodeKind,
+        size: Option<u64>,
+        content_block_count: u64,
+        metadata: &Metadata,
+    ) -> Self {
+        Self {
+            kind,
+            size,
+            created: metadata.get_created().map(|created| created.timestamp()),
+            modified: metadata.get_modified().map(|modified| modified.timestamp()),
+            content_block_count,
+            metadata: custom_metadata(metadata),
+        }
+    }
+}
+
+impl DirEntry {
+    pub(crate) fn new(name: String, stat: NodeStat) -> Self {
+        Self {
+            name,
+            kind: stat.kind,
+            size: stat.size,
+            modified: stat.modified,
+            created: stat.created,
+        }
+    }
+}
+
+impl<'a> PrivateDirectoryHelper<'a> {
+    /// Returns the kind, size, times and custom metadata of the file or directory at
+    /// `path_segments`. An empty path refers to the root directory.
+    pub async fn stat(&mut self, path_segments: &[String]) -> Result<NodeStat, WnfsUtilsError> {
+        let node = self
+            .get_node(path_segments)
+            .await
+            .map_err(wnfs_error("stat"))?;
+        self.node_stat(&node).await
+    }
+
+    /// Returns whether a file or directory exists at `path_segments`.
+    pub async fn exists(&mut self, path_segments: &[String]) -> Result<bool, WnfsUtilsError> {
+        match self.get_node(path_segments).await {
+            Ok(_) => Ok(true),
+            Err(WnfsUtilsError::NotFound(_)) => Ok(false),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Lists the directory at `path_segments` in name order, one page at a time. Pass the
+    /// `cursor` of the previous page to continue after it. Only the entries of the returned
+    /// page are loaded from the forest. `limit` must be at least 1.
+    pub async fn ls_detailed(
+        &mut self,
+        path_segments: &[String],
+        cursor: Option<&str>,
+        limit: usize,
+    ) -> Result<DirListing, WnfsUtilsError> {
+        if limit == 0 {
+            return Err(WnfsUtilsError::Other(
+                "ls_detailed: limit must be at least 1".into(),
+            ));
+        }
+        let dir = self
+            .get_node(path_segments)
+            .await
+            .map_err(wnfs_error("ls_detailed"))?
+            .as_dir()
+            .map_err(wnfs_error("ls_detailed"))?;
+        let mut names = dir
+            .get_entries()
+            .filter(|name| cursor.is_none_or(|cursor| name.as_str() > cursor))
+            .peekable();
+        let mut entries = Vec::new();
+        while entries.len() < limit {
+            let Some(name) = names.next() else {
+                break;
+            };
+            let node = dir
+                .lookup_node(name, true, &self.forest, &self.store)
+                .await
+                .map_err(wnfs_error("ls_detailed"))?
+                .ok_or_else(|| WnfsUtilsError::NotFound(name.to_owned()))
+                .map_err(wnfs_error("ls_detailed"))?;
+            let stat = self.node_stat(&node).await?;
+            entries.push(DirEntry::new(name.to_owned(), stat));
+        }
+        let cursor = match names.peek() {
+            Some(_) => entries.last().map(|entry| entry.name.to_owned()),
+            None => None,
+        };
+        Ok(DirListing { entries, cursor })
+    }
+
+    pub(crate) async fn node_stat(&self, node: &PrivateNode) -> Result<NodeStat, WnfsUtilsError> {
+        Ok(match node {
+            PrivateNode::File(file) => NodeStat::new(
+                NodeKind::File,
+                Some(self.file_size(file).await?),
+                content_block_count(file),
+                file.get_metadata(),
+            ),
+            PrivateNode::Dir(dir) => NodeStat::new(NodeKind::Dir, None, 0, dir.get_metadata()),
+        })
+    }
+
+    // Computes the exact content size of a file by fetching only its last content block.
+    pub(crate) async fn file_size(&self, file: &PrivateFile) -> Result<u64, WnfsUtilsError> {
+        let last_block_index = file
+            .get_content_size_upper_bound()
+            .div_ceil(MAX_BLOCK_CONTENT_SIZE)
+            .saturating_sub(1);
+        let stream = file.stream_content(last_block_index, &self.forest, &self.store);
+        pin_mut!(stream);
+        let mut size = (last_block_index * MAX_BLOCK_CONTENT_SIZE) as u64;
+        while let Some(block) = stream.next().await {
+            size += block.map_err(wnfs_error("file_size"))?.len() as u64;
+        }
+        Ok(size)
+    }
+
+    pub async fn stat_async(
+        &mut self,
+        path_segments: &[String],
+    ) -> Result<NodeStat, WnfsUtilsError> {
+        self.stat(path_segments).await
+    }
+
+    pub async fn ls_detailed_async(
+        &mut self,
+        path_segments: &[String],
+        cursor: Option<&str>,
+        limit: usize,
+    ) -> Result<DirListing, WnfsUtilsError> {
+        self.ls_detailed(path_segments, cursor, limit).await
+    }
+
+    pub async fn exists_async(&mut self, path_segments: &[String]) -> Result<bool, WnfsUtilsError> {
+        self.exists(path_segments).await
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Functions
+//--------------------------------------------------------------------------------------------------
+
+// Number of content blocks `file` is stored in, as recorded in the file node. External content
+// accounts for exactly `MAX_BLOCK_CONTENT_SIZE` bytes of the size upper bound per block, while
+// inline content is smaller than a block and stored within the file node itself.
+pub(crate) fn content_block_count(file: &PrivateFile) -> u64 {
+    (file.get_content_size_upper_bound() / MAX_BLOCK_CONTENT_SIZE) as u64
+}
+
+// Returns the metadata entries other than the times maintained by wnfs.
+pub(crate) fn custom_metadata(metadata: &Metadata) -> BTreeMap<String, Ipld> {
+    metadata
+        .0
+        .iter()
+        .filter(|(key, _)| !WNFS_METADATA_KEYS.contains(&key.as_str()))
+        .map(|(key, value)| (key.to_owned(), value.to_owned()))
+        .collect()
+}
+
+#[cfg(test)]
+mod stat_tests;
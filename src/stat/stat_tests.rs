@@ -0,0 +1,126 @@
+use crate::blockstore::FFIFriendlyBlockStore;
+use crate::error::WnfsUtilsError;
+use crate::memorystore::MemoryBlockStore;
+use crate::private_forest::PrivateDirectoryHelper;
+use crate::stat::NodeKind;
+
+#[tokio::test]
+async fn stat_files_and_directories() {
+    let store = MemoryBlockStore::new();
+    let blockstore = &mut FFIFriendlyBlockStore::new(Box::new(store));
+    let (helper, _, _) = &mut PrivateDirectoryHelper::init(blockstore, vec![0; 32])
+        .await
+        .unwrap();
+
+    let small = ["root".to_string(), "small.txt".to_string()];
+    helper
+        .write_file(&small, b"hello".to_vec(), 1_700_000_000)
+        .await
+        .unwrap();
+    let stat = helper.stat(&small).await.unwrap();
+    assert_eq!(stat.kind, NodeKind::File);
+    assert_eq!(stat.size, Some(5));
+    assert_eq!(stat.modified, Some(1_700_000_000));
+    assert!(stat.metadata.is_empty());
+
+    let big = ["root".to_string(), "big.bin".to_string()];
+    helper
+        .write_file(&big, vec![1; 600 * 1024 + 3], 0)
+        .await
+        .unwrap();
+    let stat = helper.stat(&big).await.unwrap();
+    assert_eq!(stat.size, Some(600 * 1024 + 3));
+    assert_eq!(stat.content_block_count, 3);
+
+    let empty = ["root".to_string(), "empty.txt".to_string()];
+    helper.write_file(&empty, Vec::new(), 0).await.unwrap();
+    let stat = helper.stat(&empty).await.unwrap();
+    assert_eq!(stat.size, Some(0));
+    assert_eq!(stat.content_block_count, 0);
+
+    let stat = helper.stat(&["root".into()]).await.unwrap();
+    assert_eq!(stat.kind, NodeKind::Dir);
+    assert_eq!(stat.size, None);
+    assert_eq!(stat.content_block_count, 0);
+
+    let err = helper
+        .stat(&["root".into(), "missing".into()])
+        .await
+        .unwrap_err();
+    assert!(matches!(err, WnfsUtilsError::NotFound(_)));
+}
+
+#[tokio::test]
+async fn exists_checks_paths() {
+    let store = MemoryBlockStore::new();
+    let blockstore = &mut FFIFriendlyBlockStore::new(Box::new(store));
+    let (helper, _, _) = &mut PrivateDirectoryHelper::init(blockstore, vec![0; 32])
+        .await
+        .unwrap();
+    helper
+        .write_file(&["root".into(), "a.txt".into()], b"a".to_vec(), 0)
+        .await
+        .unwrap();
+
+    assert!(helper.exists(&["root".into()]).await.unwrap());
+    assert!(helper
+        .exists(&["root".into(), "a.txt".into()])
+        .await
+        .unwrap());
+    assert!(!helper
+        .exists(&["root".into(), "b.txt".into()])
+        .await
+        .unwrap());
+    assert!(!helper
+        .exists(&["other".into(), "a.txt".into()])
+        .await
+        .unwrap());
+}
+
+#[tokio::test]
+async fn ls_detailed_pages_through_entries() {
+    let store = MemoryBlockStore::new();
+    let blockstore = &mut FFIFriendlyBlockStore::new(Box::new(store));
+    let (helper, _, _) = &mut PrivateDirectoryHelper::init(blockstore, vec![0; 32])
+        .await
+        .unwrap();
+    for i in 0..5 {
+        helper
+            .write_file(
+                &["root".into(), format!("file_{}.txt", i)],
+                vec![0; i],
+                1_700_000_000,
+            )
+            .await
+            .unwrap();
+    }
+    helper.mkdir(&["root".into(), "dir".into()]).await.unwrap();
+
+    let page = helper.ls_detailed(&["root".into()], None, 4).await.unwrap();
+    let names: Vec<&str> = page.entries.iter().map(|e| e.name.as_str()).collect();
+    assert_eq!(names, vec!["dir", "file_0.txt", "file_1.txt", "file_2.txt"]);
+    assert_eq!(page.entries[0].kind, NodeKind::Dir);
+    assert_eq!(page.entries[0].size, None);
+    assert_eq!(page.entries[3].kind, NodeKind::File);
+    assert_eq!(page.entries[3].size, Some(2));
+    assert_eq!(page.entries[3].modified, Some(1_700_000_000));
+    assert_eq!(page.cursor.as_deref(), Some("file_2.txt"));
+
+    let page = helper
+        .ls_detailed(&["root".into()], page.cursor.as_deref(), 4)
+        .await
+        .unwrap();
+    let names: Vec<&str> = page.entries.iter().map(|e| e.name.as_str()).collect();
+    assert_eq!(names, vec!["file_3.txt", "file_4.txt"]);
+    assert_eq!(page.cursor, None);
+
+    let err = helper
+        .ls_detailed(&["root".into()], None, 0)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, WnfsUtilsError::Other(_)));
+
+    let json = serde_json::to_value(&page).unwrap();
+    assert_eq!(json["entries"][0]["kind"], "file");
+    assert_eq!(json["entries"][1]["size"], 4);
+}
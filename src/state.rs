@@ -0,0 +1,185 @@
+//! Drive state that isn't part of the user's files, like unfinished uploads, the quota and
+//! the share registry. It is kept in a private directory of its own next to the root
+//! directory, shared with the drive owner under a separate label. So it never shows up in
+//! listings, walks, diffs or exports, isn't included in shares of the root directory and
+//! isn't restored or rolled back with it.
+
+use chrono::Utc;
+use std::rc::Rc;
+use wnfs::private::{
+    forest::{hamt::HamtForest, traits::PrivateForest},
+    share::{recipient, sharer},
+    PrivateDirectory, PrivateNode,
+};
+
+use crate::blockstore::FFIFriendlyBlockStore;
+use crate::error::WnfsUtilsError;
+use crate::private_forest::{
+    share_access_key, wnfs_error, PrivateDirectoryHelper, SeededExchangeKey,
+};
+
+//--------------------------------------------------------------------------------------------------
+// Implementations
+//--------------------------------------------------------------------------------------------------
+
+impl<'a> PrivateDirectoryHelper<'a> {
+    // Finds the latest revision of the state directory of the drive of `wnfs_key` in `forest`,
+    // `None` if no state was written yet. `exchange_keypair` is the one derived from `wnfs_key`.
+    pub(crate) async fn load_state_dir(
+        forest: &Rc<HamtForest>,
+        store: &FFIFriendlyBlockStore<'a>,
+        wnfs_key: &[u8],
+        exchange_keypair: &SeededExchangeKey,
+    ) -> Result<Option<Rc<PrivateDirectory>>, WnfsUtilsError> {
+        let exchange_public_key = exchange_keypair.encode_public_key();
+        let state_did = state_did(wnfs_key);
+        let Some(counter) = recipient::find_latest_share_counter(
+            0,
+            1000,
+            &exchange_public_key,
+            &state_did,
+            forest,
+            store,
+        )
+        .await
+        .map_err(wnfs_error("load_state_dir"))?
+        else {
+            return Ok(None);
+        };
+        let name = sharer::create_share_name(counter, &state_did, &exchange_public_key, forest);
+        let state_dir = recipient::receive_share(&name, exchange_keypair, forest, store)
+            .await
+            .map_err(wnfs_error("load_state_dir"))?
+            .search_latest(forest, store)
+            .await
+            .map_err(wnfs_error("load_state_dir"))?
+            .as_dir()
+            .map_err(wnfs_error("load_state_dir"))?;
+        Ok(Some(state_dir))
+    }
+
+    // Looks up the node at `path_segments` in the state directory.
+    pub(crate) async fn get_state_node(
+        &self,
+        path_segments: &[String],
+    ) -> Result<Option<PrivateNode>, WnfsUtilsError> {
+        let Some(state_dir) = &self.state_dir else {
+            return Ok(None);
+        };
+        state_dir
+            .get_node(path_segments, true, &self.forest, &self.store)
+            .await
+            .map_err(wnfs_error("get_state_node"))
+    }
+
+    // Reads the state file at `path_segments`, `None` if there is none.
+    pub(crate) async fn read_state_file(
+        &self,
+        path_segments: &[String],
+    ) -> Result<Option<Vec<u8>>, WnfsUtilsError> {
+        let Some(node) = self.get_state_node(path_segments).await? else {
+            return Ok(None);
+        };
+        let content = node
+            .as_file()
+            .map_err(wnfs_error("read_state_file"))?
+            .get_content(&self.forest, &self.store)
+            .await
+            .map_err(wnfs_error("read_state_file"))?;
+        Ok(Some(content))
+    }
+
+    // Lists the names in the state directory at `path_segments`, none if it doesn't exist.
+    pub(crate) async fn ls_state(
+        &self,
+        path_segments: &[String],
+    ) -> Result<Vec<String>, WnfsUtilsError> {
+        match self.get_state_node(path_segments).await? {
+            Some(PrivateNode::Dir(dir)) => Ok(dir.get_entries().cloned().collect()),
+            Some(PrivateNode::File(_)) => Err(WnfsUtilsError::NotADirectory),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    pub(crate) async fn apply_write_state_file(
+        &mut self,
+        path_segments: &[String],
+        content: Vec<u8>,
+    ) -> Result<(), WnfsUtilsError> {
+        self.check_writable()?;
+        let mut state_dir = match &self.state_dir {
+            Some(state_dir) => Rc::clone(state_dir),
+            None => self.create_state_dir().await?,
+        };
+        state_dir
+            .write(
+                path_segments,
+                true,
+                Utc::now(),
+                content,
+                &mut self.forest,
+                &mut self.store,
+                &mut self.rng,
+            )
+            .await
+            .map_err(wnfs_error("write_state_file"))?;
+        self.state_dir = Some(state_dir);
+        Ok(())
+    }
+
+    pub(crate) async fn apply_rm_state(
+        &mut self,
+        path_segments: &[String],
+    ) -> Result<(), WnfsUtilsError> {
+        self.check_writable()?;
+        let Some(state_dir) = &mut self.state_dir else {
+            return Err(WnfsUtilsError::NotFound(path_segments.join("/")));
+        };
+        state_dir
+            .rm(path_segments, true, &mut self.forest, &mut self.store)
+            .await
+            .map_err(wnfs_error("rm_state"))?;
+        Ok(())
+    }
+
+    // Creates the state directory and shares it with the drive owner under its own label.
+    async fn create_state_dir(&mut self) -> Result<Rc<PrivateDirectory>, WnfsUtilsError> {
+        let state_dir = PrivateDirectory::new_and_store(
+            &self.forest.empty_name(),
+            Utc::now(),
+            &mut self.forest,
+            &mut self.store,
+            &mut self.rng,
+        )
+        .await
+        .map_err(wnfs_error("create_state_dir"))?;
+        let access_key = state_dir
+            .as_node()
+            .store(&mut self.forest, &mut self.store, &mut self.rng)
+            .await
+            .map_err(wnfs_error("create_state_dir"))?;
+        share_access_key(
+            &access_key,
+            &self.exchange_keypair.encode_public_key(),
+            &state_did(&self.wnfs_key),
+            &mut self.forest,
+            &mut self.store,
+        )
+        .await
+        .map_err(wnfs_error("create_state_dir"))?;
+        Ok(state_dir)
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Functions
+//--------------------------------------------------------------------------------------------------
+
+// Label the state directory is shared with the drive owner under. The root directory is
+// shared under the hex of the wnfs key itself.
+fn state_did(wnfs_key: &[u8]) -> String {
+    format!(
+        "{}:state",
+        PrivateDirectoryHelper::bytes_to_hex_str(wnfs_key)
+    )
+}
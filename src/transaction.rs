@@ -0,0 +1,190 @@
+use futures::AsyncRead;
+use libipld::Cid;
+use std::rc::Rc;
+use wnfs::private::{forest::hamt::HamtForest, PrivateDirectory};
+
+use crate::error::WnfsUtilsError;
+use crate::private_forest::PrivateDirectoryHelper;
+use crate::usage::Quota;
+
+/// Queues operations against the in-memory root directory and forest of a helper and
+/// persists them with a single forest commit. If the transaction is dropped or rolled
+/// back instead of committed, the helper is restored to its state before the transaction.
+pub struct Transaction<'h, 'a> {
+    helper: &'h mut PrivateDirectoryHelper<'a>,
+    snapshot: Option<Snapshot>,
+}
+
+// State of a helper before a transaction.
+struct Snapshot {
+    forest: Rc<HamtForest>,
+    root_dir: Rc<PrivateDirectory>,
+    state_dir: Option<Rc<PrivateDirectory>>,
+    quota: Option<Quota>,
+}
+
+/// A single operation of `PrivateDirectoryHelper::batch`.
+#[derive(Debug, Clone)]
+pub enum BatchOperation {
+    WriteFile {
+        path_segments: Vec<String>,
+        content: Vec<u8>,
+        modification_time_seconds: i64,
+    },
+    Mkdir {
+        path_segments: Vec<String>,
+    },
+    Rm {
+        path_segments: Vec<String>,
+    },
+    Mv {
+        source_path_segments: Vec<String>,
+        target_path_segments: Vec<String>,
+    },
+    Cp {
+        source_path_segments: Vec<String>,
+        target_path_segments: Vec<String>,
+    },
+}
+
+//--------------------------------------------------------------------------------------------------
+// Implementations
+//--------------------------------------------------------------------------------------------------
+
+impl<'h, 'a> Transaction<'h, 'a> {
+    pub async fn write_file(
+        &mut self,
+        path_segments: &[String],
+        content: Vec<u8>,
+        modification_time_seconds: i64,
+    ) -> Result<(), WnfsUtilsError> {
+        self.helper
+            .apply_write_file(path_segments, content, modification_time_seconds)
+            .await
+    }
+
+    pub async fn write_file_stream(
+        &mut self,
+        path_segments: &[String],
+        content: impl AsyncRead + Unpin,
+        modification_time_seconds: i64,
+    ) -> Result<(), WnfsUtilsError> {
+        self.helper
+            .apply_write_file_stream(path_segments, content, modification_time_seconds)
+            .await
+    }
+
+    pub async fn mkdir(&mut self, path_segments: &[String]) -> Result<(), WnfsUtilsError> {
+        self.helper.apply_mkdir(path_segments).await
+    }
+
+    pub async fn rm(&mut self, path_segments: &[String]) -> Result<(), WnfsUtilsError> {
+        self.helper.apply_rm(path_segments).await
+    }
+
+    pub async fn mv(
+        &mut self,
+        source_path_segments: &[String],
+        target_path_segments: &[String],
+    ) -> Result<(), WnfsUtilsError> {
+        self.helper
+            .apply_mv(source_path_segments, target_path_segments)
+            .await
+    }
+
+    pub async fn cp(
+        &mut self,
+        source_path_segments: &[String],
+        target_path_segments: &[String],
+    ) -> Result<(), WnfsUtilsError> {
+        self.helper
+            .apply_cp(source_path_segments, target_path_segments)
+            .await
+    }
+
+    pub async fn apply(&mut self, operation: BatchOperation) -> Result<(), WnfsUtilsError> {
+        match operation {
+            BatchOperation::WriteFile {
+                path_segments,
+                content,
+                modification_time_seconds,
+            } => {
+                self.write_file(&path_segments, content, modification_time_seconds)
+                    .await
+            }
+            BatchOperation::Mkdir { path_segments } => self.mkdir(&path_segments).await,
+            BatchOperation::Rm { path_segments } => self.rm(&path_segments).await,
+            BatchOperation::Mv {
+                source_path_segments,
+                target_path_segments,
+            } => self.mv(&source_path_segments, &target_path_segments).await,
+            BatchOperation::Cp {
+                source_path_segments,
+                target_path_segments,
+            } => self.cp(&source_path_segments, &target_path_segments).await,
+        }
+    }
+
+    /// Gives access to the helper, e.g. to read files written earlier in the transaction.
+    pub fn helper(&mut self) -> &mut PrivateDirectoryHelper<'a> {
+        self.helper
+    }
+
+    /// Stores the root directory and the forest once and returns the new forest cid.
+    /// On failure the helper is restored to its state before the transaction.
+    pub async fn commit(mut self) -> Result<Cid, WnfsUtilsError> {
+        let forest_cid = self.helper.store_root_dir("commit").await?;
+        self.snapshot = None;
+        Ok(forest_cid)
+    }
+
+    /// Discards all operations of the transaction.
+    pub fn rollback(self) {}
+}
+
+impl<'h, 'a> Drop for Transaction<'h, 'a> {
+    fn drop(&mut self) {
+        if let Some(snapshot) = self.snapshot.take() {
+            self.helper.forest = snapshot.forest;
+            self.helper.root_dir = snapshot.root_dir;
+            self.helper.state_dir = snapshot.state_dir;
+            self.helper.quota = snapshot.quota;
+        }
+    }
+}
+
+impl<'a> PrivateDirectoryHelper<'a> {
+    /// Starts a transaction. Operations applied through it are only persisted by `commit`.
+    pub fn transaction(&mut self) -> Transaction<'_, 'a> {
+        let snapshot = Some(Snapshot {
+            forest: Rc::clone(&self.forest),
+            root_dir: Rc::clone(&self.root_dir),
+            state_dir: self.state_dir.clone(),
+            quota: self.quota,
+        });
+        Transaction {
+            helper: self,
+            snapshot,
+        }
+    }
+
+    /// Applies all operations and commits the forest once. Either all operations are
+    /// persisted or none.
+    pub async fn batch(&mut self, operations: Vec<BatchOperation>) -> Result<Cid, WnfsUtilsError> {
+        let mut transaction = self.transaction();
+        for operation in operations {
+            transaction.apply(operation).await?;
+        }
+        transaction.commit().await
+    }
+
+    pub async fn batch_async(
+        &mut self,
+        operations: Vec<BatchOperation>,
+    ) -> Result<Cid, WnfsUtilsError> {
+        self.batch(operations).await
+    }
+}
+
+#[cfg(test)]
+mod transaction_tests;
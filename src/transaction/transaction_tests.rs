@@ -0,0 +1,94 @@
+use crate::blockstore::FFIFriendlyBlockStore;
+use crate::memorystore::MemoryBlockStore;
+use crate::private_forest::PrivateDirectoryHelper;
+use crate::transaction::BatchOperation;
+
+#[tokio::test]
+async fn transaction_commits_once() {
+    let store = MemoryBlockStore::new();
+    let blockstore = &mut FFIFriendlyBlockStore::new(Box::new(store));
+    let (helper, _, _) = &mut PrivateDirectoryHelper::init(blockstore, vec![0; 32])
+        .await
+        .unwrap();
+
+    let mut transaction = helper.transaction();
+    for i in 0..10 {
+        transaction
+            .write_file(
+                &["root".into(), format!("file_{}.txt", i)],
+                format!("content {}", i).into_bytes(),
+                0,
+            )
+            .await
+            .unwrap();
+    }
+    transaction
+        .mkdir(&["root".into(), "dir".into()])
+        .await
+        .unwrap();
+    let cid = transaction.commit().await.unwrap();
+
+    let reloaded = &mut PrivateDirectoryHelper::load_with_wnfs_key(blockstore, cid, vec![0; 32])
+        .await
+        .unwrap();
+    let ls_result = reloaded.ls_files(&["root".into()]).await.unwrap();
+    assert_eq!(ls_result.len(), 11);
+    let content = reloaded
+        .read_file(&["root".into(), "file_3.txt".into()])
+        .await
+        .unwrap();
+    assert_eq!(content, b"content 3".to_vec());
+}
+
+#[tokio::test]
+async fn dropped_transaction_is_rolled_back() {
+    let store = MemoryBlockStore::new();
+    let blockstore = &mut FFIFriendlyBlockStore::new(Box::new(store));
+    let (helper, _, _) = &mut PrivateDirectoryHelper::init(blockstore, vec![0; 32])
+        .await
+        .unwrap();
+    helper
+        .write_file(&["root".into(), "kept.txt".into()], b"kept".to_vec(), 0)
+        .await
+        .unwrap();
+
+    let mut transaction = helper.transaction();
+    transaction
+        .write_file(&["root".into(), "discarded.txt".into()], b"x".to_vec(), 0)
+        .await
+        .unwrap();
+    transaction
+        .rm(&["root".into(), "kept.txt".into()])
+        .await
+        .unwrap();
+    transaction.rollback();
+
+    let ls_result = helper.ls_files(&["root".into()]).await.unwrap();
+    assert_eq!(ls_result.len(), 1);
+    assert_eq!(ls_result[0].0, "kept.txt");
+}
+
+#[tokio::test]
+async fn failed_batch_is_not_persisted() {
+    let store = MemoryBlockStore::new();
+    let blockstore = &mut FFIFriendlyBlockStore::new(Box::new(store));
+    let (helper, _, _) = &mut PrivateDirectoryHelper::init(blockstore, vec![0; 32])
+        .await
+        .unwrap();
+    helper.mkdir(&["root".into()]).await.unwrap();
+
+    let result = helper
+        .batch(vec![
+            BatchOperation::Mkdir {
+                path_segments: vec!["root".into(), "a".into()],
+            },
+            BatchOperation::Rm {
+                path_segments: vec!["root".into(), "missing".into()],
+            },
+        ])
+        .await;
+    assert!(result.is_err());
+
+    let ls_result = helper.ls_files(&["root".into()]).await.unwrap();
+    assert!(ls_result.is_empty());
+}
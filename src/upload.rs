@@ -0,0 +1,272 @@
+//! Resumable chunked uploads. Chunks are written as private files into a staging directory
+//! in the drive state, next to a record describing the upload, so an interrupted upload can
+//! be resumed from the last forest cid after an app restart. Finishing an upload streams the
+//! chunks in order into a normal private file and removes the staging directory.
+
+use futures::{stream, StreamExt, TryStreamExt};
+use libipld::Cid;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::rc::Rc;
+
+use crate::error::WnfsUtilsError;
+use crate::private_forest::{wnfs_error, PrivateDirectoryHelper};
+
+// Directory of the drive state holding the staging directories of unfinished uploads.
+const UPLOADS_DIR: &str = "uploads";
+
+const UPLOAD_RECORD: &str = "upload.json";
+
+/// Record of an upload, persisted in its staging directory.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UploadSession {
+    pub upload_id: String,
+    pub path_segments: Vec<String>,
+    pub modification_time_seconds: i64,
+}
+
+/// State of an upload, used to find the chunks still to be sent when resuming.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UploadStatus {
+    pub session: UploadSession,
+    pub chunks_received: Vec<u64>,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Implementations
+//--------------------------------------------------------------------------------------------------
+
+impl<'a> PrivateDirectoryHelper<'a> {
+    /// Starts an upload into `path_segments`. Returns the upload id and the new forest cid.
+    pub async fn begin_upload(
+        &mut self,
+        path_segments: &[String],
+        modification_time_seconds: i64,
+    ) -> Result<(String, Cid), WnfsUtilsError> {
+        if path_segments.is_empty() {
+            return Err(WnfsUtilsError::InvalidPath);
+        }
+        let mut id = [0u8; 16];
+        self.rng.fill_bytes(&mut id);
+        let session = UploadSession {
+            upload_id: id.iter().map(|b| format!("{:02x}", b)).collect(),
+            path_segments: path_segments.to_vec(),
+            modification_time_seconds,
+        };
+        let record = serde_json::to_vec(&session)
+            .map_err(|e| WnfsUtilsError::Other(e.to_string()))
+            .map_err(wnfs_error("begin_upload"))?;
+        self.apply_write_state_file(
+            &upload_path(&session.upload_id, Some(UPLOAD_RECORD)),
+            record,
+        )
+        .await?;
+        let cid = self.store_root_dir("begin_upload").await?;
+        Ok((session.upload_id, cid))
+    }
+
+    /// Stores chunk `index` of an upload. Sending a chunk again replaces it.
+    pub async fn upload_chunk(
+        &mut self,
+        upload_id: &str,
+        index: u64,
+        content: Vec<u8>,
+    ) -> Result<Cid, WnfsUtilsError> {
+        self.load_upload(upload_id).await?;
+        self.apply_write_state_file(&upload_path(upload_id, Some(&index.to_string())), content)
+            .await?;
+        self.store_root_dir("upload_chunk").await
+    }
+
+    /// Returns the upload record and the indices of the chunks received so far.
+    pub async fn upload_status(&mut self, upload_id: &str) -> Result<UploadStatus, WnfsUtilsError> {
+        let session = self.load_upload(upload_id).await?;
+        let mut chunks_received: Vec<u64> = self
+            .ls_state(&upload_path(upload_id, None))
+            .await?
+            .into_iter()
+            .filter_map(|name| name.parse().ok())
+            .collect();
+        chunks_received.sort_unstable();
+        Ok(UploadStatus {
+            session,
+            chunks_received,
+        })
+    }
+
+    /// Lists the uploads that were started but neither finished nor aborted.
+    pub async fn list_uploads(&mut self) -> Result<Vec<UploadSession>, WnfsUtilsError> {
+        let upload_ids = self.ls_state(&[UPLOADS_DIR.to_string()]).await?;
+        let mut sessions = Vec::with_capacity(upload_ids.len());
+        for upload_id in upload_ids {
+            sessions.push(self.load_upload(&upload_id).await?);
+        }
+        Ok(sessions)
+    }
+
+    /// Writes chunks `0..chunk_count` of an upload in order into its target file and removes
+    /// the staging directory, in a single forest commit. Fails with `IncompleteUpload` if any
+    /// of the chunks is missing.
+    pub async fn finish_upload(
+        &mut self,
+        upload_id: &str,
+        chunk_count: u64,
+    ) -> Result<Cid, WnfsUtilsError> {
+        self.check_writable()?;
+        let status = self.upload_status(upload_id).await?;
+        let missing: Vec<u64> = (0..chunk_count)
+            .filter(|index| status.chunks_received.binary_search(index).is_err())
+            .collect();
+        if !missing.is_empty() {
+            return Err(WnfsUtilsError::IncompleteUpload(missing));
+        }
+
+        let mut chunks = Vec::with_capacity(chunk_count as usize);
+        for index in 0..chunk_count {
+            let chunk = self
+                .get_state_node(&upload_path(upload_id, Some(&index.to_string())))
+                .await?
+                .ok_or(WnfsUtilsError::IncompleteUpload(vec![index]))?
+                .as_file()
+                .map_err(wnfs_error("finish_upload"))?;
+            chunks.push(chunk);
+        }
+        // Copy-on-write snapshots, so the chunks stay readable while the target is written.
+        let forest = Rc::clone(&self.forest);
+        let store = self.store.to_owned();
+        let (forest, store) = (&forest, &store);
+        let content = Box::pin(stream::iter(chunks).then(move |chunk| async move {
+            chunk
+                .get_content(forest, store)
+                .await
+                .map_err(std::io::Error::other)
+        }))
+        .into_async_read();
+
+        let mut transaction = self.transaction();
+        transaction
+            .write_file_stream(
+                &status.session.path_segments,
+                content,
+                status.session.modification_time_seconds,
+            )
+            .await?;
+        transaction
+            .helper()
+            .apply_rm_state(&upload_path(upload_id, None))
+            .await?;
+        transaction.commit().await
+    }
+
+    /// Discards an upload and its chunks.
+    pub async fn abort_upload(&mut self, upload_id: &str) -> Result<Cid, WnfsUtilsError> {
+        self.load_upload(upload_id).await?;
+        self.apply_rm_state(&upload_path(upload_id, None)).await?;
+        self.store_root_dir("abort_upload").await
+    }
+
+    // Merges the unfinished uploads of both sides of a merge. An upload started on either
+    // side since `base` is kept with the chunks received on both sides, and one finished or
+    // aborted on either side stays removed.
+    pub(crate) async fn apply_merge_uploads<'s>(
+        &mut self,
+        base: &PrivateDirectoryHelper<'s>,
+        ours: &PrivateDirectoryHelper<'s>,
+        theirs: &PrivateDirectoryHelper<'s>,
+    ) -> Result<(), WnfsUtilsError> {
+        let uploads_dir = [UPLOADS_DIR.to_string()];
+        let base_ids: BTreeSet<String> = base.ls_state(&uploads_dir).await?.into_iter().collect();
+        let ours_ids: BTreeSet<String> = ours.ls_state(&uploads_dir).await?.into_iter().collect();
+        let theirs_ids: BTreeSet<String> =
+            theirs.ls_state(&uploads_dir).await?.into_iter().collect();
+        let removed = |upload_id: &String| {
+            base_ids.contains(upload_id)
+                && !(ours_ids.contains(upload_id) && theirs_ids.contains(upload_id))
+        };
+        for upload_id in self.ls_state(&uploads_dir).await? {
+            if removed(&upload_id) {
+                self.apply_rm_state(&upload_path(&upload_id, None)).await?;
+            }
+        }
+        for (source, upload_ids) in [(ours, &ours_ids), (theirs, &theirs_ids)] {
+            for upload_id in upload_ids.iter().filter(|upload_id| !removed(*upload_id)) {
+                for name in source.ls_state(&upload_path(upload_id, None)).await? {
+                    let path_segments = upload_path(upload_id, Some(&name));
+                    if self.get_state_node(&path_segments).await?.is_some() {
+                        continue;
+                    }
+                    if let Some(content) = source.read_state_file(&path_segments).await? {
+                        self.apply_write_state_file(&path_segments, content).await?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn load_upload(&mut self, upload_id: &str) -> Result<UploadSession, WnfsUtilsError> {
+        let record = self
+            .read_state_file(&upload_path(upload_id, Some(UPLOAD_RECORD)))
+            .await?
+            .ok_or_else(|| WnfsUtilsError::NotFound(format!("upload {}", upload_id)))?;
+        serde_json::from_slice(&record)
+            .map_err(|e| WnfsUtilsError::Other(e.to_string()))
+            .map_err(wnfs_error("load_upload"))
+    }
+
+    pub async fn begin_upload_async(
+        &mut self,
+        path_segments: &[String],
+        modification_time_seconds: i64,
+    ) -> Result<(String, Cid), WnfsUtilsError> {
+        self.begin_upload(path_segments, modification_time_seconds)
+            .await
+    }
+
+    pub async fn upload_chunk_async(
+        &mut self,
+        upload_id: &str,
+        index: u64,
+        content: Vec<u8>,
+    ) -> Result<Cid, WnfsUtilsError> {
+        self.upload_chunk(upload_id, index, content).await
+    }
+
+    pub async fn upload_status_async(
+        &mut self,
+        upload_id: &str,
+    ) -> Result<UploadStatus, WnfsUtilsError> {
+        self.upload_status(upload_id).await
+    }
+
+    pub async fn list_uploads_async(&mut self) -> Result<Vec<UploadSession>, WnfsUtilsError> {
+        self.list_uploads().await
+    }
+
+    pub async fn finish_upload_async(
+        &mut self,
+        upload_id: &str,
+        chunk_count: u64,
+    ) -> Result<Cid, WnfsUtilsError> {
+        self.finish_upload(upload_id, chunk_count).await
+    }
+
+    pub async fn abort_upload_async(&mut self, upload_id: &str) -> Result<Cid, WnfsUtilsError> {
+        self.abort_upload(upload_id).await
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Functions
+//--------------------------------------------------------------------------------------------------
+
+// Path of the staging directory of an upload in the drive state, or of an entry within it.
+fn upload_path(upload_id: &str, name: Option<&str>) -> Vec<String> {
+    let mut path_segments = vec![UPLOADS_DIR.to_string(), upload_id.to_string()];
+    path_segments.extend(name.map(|name| name.to_string()));
+    path_segments
+}
+
+#[cfg(test)]
+mod upload_tests;
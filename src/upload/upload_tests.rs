@@ -0,0 +1,81 @@
+use crate::blockstore::FFIFriendlyBlockStore;
+use crate::error::WnfsUtilsError;
+use crate::memorystore::MemoryBlockStore;
+use crate::private_forest::PrivateDirectoryHelper;
+
+#[tokio::test]
+async fn upload_resumes_after_reload() {
+    let store = MemoryBlockStore::new();
+    let blockstore = &mut FFIFriendlyBlockStore::new(Box::new(store));
+    let (helper, _, _) = &mut PrivateDirectoryHelper::init(blockstore, vec![0; 32])
+        .await
+        .unwrap();
+    let path = ["root".to_string(), "video.mp4".to_string()];
+
+    let (upload_id, _) = helper.begin_upload(&path, 1_700_000_000).await.unwrap();
+    helper
+        .upload_chunk(&upload_id, 0, vec![0; 1000])
+        .await
+        .unwrap();
+    let cid = helper
+        .upload_chunk(&upload_id, 2, vec![2; 1000])
+        .await
+        .unwrap();
+
+    // The app restarts and reopens the drive from the last forest cid.
+    let helper = &mut PrivateDirectoryHelper::load_with_wnfs_key(blockstore, cid, vec![0; 32])
+        .await
+        .unwrap();
+    let uploads = helper.list_uploads().await.unwrap();
+    assert_eq!(uploads.len(), 1);
+    assert_eq!(uploads[0].upload_id, upload_id);
+    let status = helper.upload_status(&upload_id).await.unwrap();
+    assert_eq!(status.session.path_segments, path.to_vec());
+    assert_eq!(status.chunks_received, vec![0, 2]);
+    // Staged chunks are kept out of the user's files.
+    assert!(helper.ls_files(&[]).await.unwrap().is_empty());
+
+    let err = helper.finish_upload(&upload_id, 3).await.unwrap_err();
+    assert_eq!(err, WnfsUtilsError::IncompleteUpload(vec![1]));
+    assert_eq!(err.code(), 11);
+
+    helper
+        .upload_chunk(&upload_id, 1, vec![1; 1000])
+        .await
+        .unwrap();
+    helper.finish_upload(&upload_id, 3).await.unwrap();
+
+    let mut expected = vec![0; 1000];
+    expected.extend(vec![1; 1000]);
+    expected.extend(vec![2; 1000]);
+    assert_eq!(helper.read_file(&path).await.unwrap(), expected);
+    assert!(helper.list_uploads().await.unwrap().is_empty());
+    let err = helper.upload_status(&upload_id).await.unwrap_err();
+    assert!(matches!(err, WnfsUtilsError::NotFound(_)));
+}
+
+#[tokio::test]
+async fn aborted_upload_leaves_no_file() {
+    let store = MemoryBlockStore::new();
+    let blockstore = &mut FFIFriendlyBlockStore::new(Box::new(store));
+    let (helper, _, _) = &mut PrivateDirectoryHelper::init(blockstore, vec![0; 32])
+        .await
+        .unwrap();
+    let path = ["root".to_string(), "aborted.bin".to_string()];
+
+    let (upload_id, _) = helper.begin_upload(&path, 0).await.unwrap();
+    helper
+        .upload_chunk(&upload_id, 0, b"partial".to_vec())
+        .await
+        .unwrap();
+    helper.abort_upload(&upload_id).await.unwrap();
+
+    assert!(helper.list_uploads().await.unwrap().is_empty());
+    let err = helper.read_file(&path).await.unwrap_err();
+    assert!(matches!(err, WnfsUtilsError::NotFound(_)));
+    let err = helper
+        .upload_chunk(&upload_id, 1, b"late".to_vec())
+        .await
+        .unwrap_err();
+    assert!(matches!(err, WnfsUtilsError::NotFound(_)));
+}
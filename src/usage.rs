@@ -0,0 +1,229 @@
+//! Disk usage of private files and directories, and an optional quota on the size of the
+//! drive. While a quota is set, the bytes used by all files are counted along with every
+//! write and persisted in the drive state, so checking a write doesn't walk the drive.
+
+use libipld::{cbor::DagCborCodec, codec::Codec, Cid, Ipld};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use wnfs::{
+    common::{BlockStore, CODEC_DAG_CBOR, MAX_BLOCK_SIZE},
+    private::{PrivateNode, MAX_BLOCK_CONTENT_SIZE},
+};
+
+use crate::error::WnfsUtilsError;
+use crate::private_forest::{wnfs_error, PrivateDirectoryHelper};
+use crate::stat::content_block_count;
+
+// State file holding the quota of the drive.
+const QUOTA_RECORD: &str = "quota.json";
+
+/// Result of `PrivateDirectoryHelper::du`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DiskUsage {
+    pub files: u64,
+    /// Number of directories below the given path.
+    pub directories: u64,
+    /// Sum of the plaintext sizes of all files.
+    pub logical_bytes: u64,
+    /// Sum of the sizes of the encrypted contents of all files. Every content block adds a
+    /// nonce and an authentication tag, inline content is encrypted as part of its file node.
+    pub encrypted_bytes: u64,
+}
+
+// The quota of the drive and the plaintext bytes all of its files use, as persisted in the
+// drive state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct Quota {
+    pub(crate) quota_bytes: u64,
+    pub(crate) used_bytes: u64,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Implementations
+//--------------------------------------------------------------------------------------------------
+
+impl DiskUsage {
+    fn add_file(&mut self, size: u64, block_count: u64) {
+        self.files += 1;
+        self.logical_bytes += size;
+        self.encrypted_bytes +=
+            size + block_count * (MAX_BLOCK_SIZE - MAX_BLOCK_CONTENT_SIZE) as u64;
+    }
+}
+
+impl<'a> PrivateDirectoryHelper<'a> {
+    /// Computes the disk usage of the file or directory at `path_segments`.
+    pub async fn du(&self, path_segments: &[String]) -> Result<DiskUsage, WnfsUtilsError> {
+        let mut usage = DiskUsage::default();
+        let node = self
+            .get_node(path_segments)
+            .await
+            .map_err(wnfs_error("du"))?;
+        // Nodes with whether they are below `path_segments`.
+        let mut pending = vec![(node, false)];
+        while let Some((node, below)) = pending.pop() {
+            match node {
+                PrivateNode::File(file) => {
+                    usage.add_file(self.file_size(&file).await?, content_block_count(&file))
+                }
+                PrivateNode::Dir(dir) => {
+                    if below {
+                        usage.directories += 1;
+                    }
+                    for name in dir.get_entries() {
+                        let child = dir
+                            .lookup_node(name, true, &self.forest, &self.store)
+                            .await
+                            .map_err(wnfs_error("du"))?
+                            .ok_or_else(|| WnfsUtilsError::NotFound(name.to_owned()))
+                            .map_err(wnfs_error("du"))?;
+                        pending.push((child, true));
+                    }
+                }
+            }
+        }
+        Ok(usage)
+    }
+
+    /// Returns the total size of all blocks reachable from `forest_cid`: the forest itself,
+    /// the encrypted nodes and contents of every revision, and shares. This is what the
+    /// drive actually occupies in the block store.
+    pub async fn stored_bytes(&self, forest_cid: Cid) -> Result<u64, WnfsUtilsError> {
+        let mut stored_bytes = 0;
+        let mut visited = HashSet::new();
+        let mut pending = vec![forest_cid];
+        while let Some(cid) = pending.pop() {
+            if !visited.insert(cid) {
+                continue;
+            }
+            let block = self
+                .store
+                .get_block(&cid)
+                .await
+                .map_err(wnfs_error("stored_bytes"))?;
+            stored_bytes += block.len() as u64;
+            if cid.codec() == CODEC_DAG_CBOR {
+                let ipld: Ipld = DagCborCodec
+                    .decode(&block)
+                    .map_err(wnfs_error("stored_bytes"))?;
+                ipld.references(&mut pending);
+            }
+        }
+        Ok(stored_bytes)
+    }
+
+    /// Limits the sum of the plaintext sizes of all files of the drive. Writes that would
+    /// exceed it fail with `QuotaExceeded`. `None` removes the limit. The quota is stored in
+    /// the drive, so it still applies after reloading. Returns the new forest cid.
+    pub async fn set_quota(&mut self, quota_bytes: Option<u64>) -> Result<Cid, WnfsUtilsError> {
+        self.check_writable()?;
+        self.quota = match quota_bytes {
+            // The only time the whole drive is counted.
+            Some(quota_bytes) => Some(Quota {
+                quota_bytes,
+                used_bytes: self.du(&[]).await?.logical_bytes,
+            }),
+            None => None,
+        };
+        if self.quota.is_none() {
+            self.apply_rm_quota().await?;
+        }
+        self.store_root_dir("set_quota").await
+    }
+
+    pub fn quota(&self) -> Option<u64> {
+        self.quota.map(|quota| quota.quota_bytes)
+    }
+
+    // Accounts for replacing `replaced_bytes` of file content with `added_bytes`. Every write
+    // goes through here, and fails with `QuotaExceeded` if it would grow the drive beyond the
+    // quota. Does nothing without a quota.
+    pub(crate) fn charge_quota(
+        &mut self,
+        replaced_bytes: u64,
+        added_bytes: u64,
+    ) -> Result<(), WnfsUtilsError> {
+        let Some(quota) = &mut self.quota else {
+            return Ok(());
+        };
+        let used_bytes = quota.used_bytes.saturating_sub(replaced_bytes) + added_bytes;
+        if added_bytes > replaced_bytes && used_bytes > quota.quota_bytes {
+            return Err(WnfsUtilsError::QuotaExceeded {
+                quota_bytes: quota.quota_bytes,
+                used_bytes: quota.used_bytes,
+                requested_bytes: added_bytes,
+            });
+        }
+        quota.used_bytes = used_bytes;
+        Ok(())
+    }
+
+    // Plaintext bytes of the files at `path_segments`, which a write there replaces. Only
+    // counted with a quota, 0 without one or if there is nothing at the path.
+    pub(crate) async fn quota_bytes_at(
+        &self,
+        path_segments: &[String],
+    ) -> Result<u64, WnfsUtilsError> {
+        if self.quota.is_none() {
+            return Ok(0);
+        }
+        match self.du(path_segments).await {
+            Ok(usage) => Ok(usage.logical_bytes),
+            Err(WnfsUtilsError::NotFound(_)) => Ok(0),
+            Err(err) => Err(err),
+        }
+    }
+
+    pub(crate) async fn read_quota(&self) -> Result<Option<Quota>, WnfsUtilsError> {
+        let Some(record) = self.read_state_file(&quota_path()).await? else {
+            return Ok(None);
+        };
+        serde_json::from_slice(&record)
+            .map_err(|e| WnfsUtilsError::Other(e.to_string()))
+            .map_err(wnfs_error("read_quota"))
+    }
+
+    // Removes the persisted quota, if any. A quota that is set is persisted with every commit.
+    pub(crate) async fn apply_rm_quota(&mut self) -> Result<(), WnfsUtilsError> {
+        if self.read_state_file(&quota_path()).await?.is_some() {
+            self.apply_rm_state(&quota_path()).await?;
+        }
+        Ok(())
+    }
+
+    pub(crate) async fn apply_write_quota(&mut self, quota: Quota) -> Result<(), WnfsUtilsError> {
+        let record = serde_json::to_vec(&quota)
+            .map_err(|e| WnfsUtilsError::Other(e.to_string()))
+            .map_err(wnfs_error("write_quota"))?;
+        self.apply_write_state_file(&quota_path(), record).await
+    }
+
+    pub async fn du_async(
+        &mut self,
+        path_segments: &[String],
+    ) -> Result<DiskUsage, WnfsUtilsError> {
+        self.du(path_segments).await
+    }
+
+    pub async fn stored_bytes_async(&mut self, forest_cid: Cid) -> Result<u64, WnfsUtilsError> {
+        self.stored_bytes(forest_cid).await
+    }
+
+    pub async fn set_quota_async(
+        &mut self,
+        quota_bytes: Option<u64>,
+    ) -> Result<Cid, WnfsUtilsError> {
+        self.set_quota(quota_bytes).await
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Functions
+//--------------------------------------------------------------------------------------------------
+
+fn quota_path() -> Vec<String> {
+    vec![QUOTA_RECORD.into()]
+}
+
+#[cfg(test)]
+mod usage_tests;
@@ -0,0 +1,167 @@
+use futures::io::Cursor;
+
+use crate::blockstore::FFIFriendlyBlockStore;
+use crate::error::WnfsUtilsError;
+use crate::memorystore::MemoryBlockStore;
+use crate::private_forest::PrivateDirectoryHelper;
+
+#[tokio::test]
+async fn du_sums_files() {
+    let store = MemoryBlockStore::new();
+    let blockstore = &mut FFIFriendlyBlockStore::new(Box::new(store.to_owned()));
+    let (helper, _, _) = &mut PrivateDirectoryHelper::init(blockstore, vec![0; 32])
+        .await
+        .unwrap();
+    helper
+        .write_file(&["root".into(), "a.txt".into()], vec![0; 100], 0)
+        .await
+        .unwrap();
+    helper
+        .write_file(
+            &["root".into(), "dir".into(), "b.bin".into()],
+            vec![1; 300 * 1024],
+            0,
+        )
+        .await
+        .unwrap();
+    let cid = helper
+        .write_file(&["other.txt".into()], vec![2; 10], 0)
+        .await
+        .unwrap();
+
+    let usage = helper.du(&["root".into()]).await.unwrap();
+    assert_eq!(usage.files, 2);
+    assert_eq!(usage.directories, 1);
+    assert_eq!(usage.logical_bytes, 100 + 300 * 1024);
+    assert!(usage.encrypted_bytes > usage.logical_bytes);
+
+    let usage = helper.du(&["root".into(), "a.txt".into()]).await.unwrap();
+    assert_eq!(usage.files, 1);
+    assert_eq!(usage.logical_bytes, 100);
+
+    let usage = helper.du(&[]).await.unwrap();
+    assert_eq!(usage.logical_bytes, 100 + 300 * 1024 + 10);
+
+    let stored_bytes = helper.stored_bytes(cid).await.unwrap();
+    assert!(stored_bytes > usage.encrypted_bytes);
+    assert!(stored_bytes <= store.used_bytes() as u64);
+}
+
+#[tokio::test]
+async fn quota_rejects_writes_and_copies() {
+    let store = MemoryBlockStore::new();
+    let blockstore = &mut FFIFriendlyBlockStore::new(Box::new(store.to_owned()));
+    let (helper, _, _) = &mut PrivateDirectoryHelper::init(blockstore, vec![0; 32])
+        .await
+        .unwrap();
+    helper.set_quota(Some(1000)).await.unwrap();
+    helper
+        .write_file(&["root".into(), "a.bin".into()], vec![0; 600], 0)
+        .await
+        .unwrap();
+
+    let used_blocks = store.len();
+    let err = helper
+        .write_file(&["root".into(), "b.bin".into()], vec![0; 500], 0)
+        .await
+        .unwrap_err();
+    assert_eq!(
+        err,
+        WnfsUtilsError::QuotaExceeded {
+            quota_bytes: 1000,
+            used_bytes: 600,
+            requested_bytes: 500,
+        }
+    );
+    assert_eq!(err.code(), 12);
+    assert_eq!(store.len(), used_blocks);
+
+    // A stream exceeding the quota is rejected before any of its blocks is stored.
+    let err = helper
+        .write_file_stream(
+            &["root".into(), "b.bin".into()],
+            Cursor::new(vec![0; 600 * 1024]),
+            0,
+        )
+        .await
+        .unwrap_err();
+    assert_eq!(
+        err,
+        WnfsUtilsError::QuotaExceeded {
+            quota_bytes: 1000,
+            used_bytes: 600,
+            requested_bytes: 600 * 1024,
+        }
+    );
+    assert_eq!(store.len(), used_blocks);
+
+    // Overwriting a file only counts the difference.
+    helper
+        .write_file(&["root".into(), "a.bin".into()], vec![0; 900], 0)
+        .await
+        .unwrap();
+    let err = helper
+        .cp(
+            &["root".into(), "a.bin".into()],
+            &["root".into(), "c.bin".into()],
+        )
+        .await
+        .unwrap_err();
+    assert!(matches!(err, WnfsUtilsError::QuotaExceeded { .. }));
+
+    helper.set_quota(None).await.unwrap();
+    helper
+        .cp(
+            &["root".into(), "a.bin".into()],
+            &["root".into(), "c.bin".into()],
+        )
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn quota_is_persisted_and_counts_every_write() {
+    let store = MemoryBlockStore::new();
+    let blockstore = &mut FFIFriendlyBlockStore::new(Box::new(store));
+    let (helper, _, _) = &mut PrivateDirectoryHelper::init(blockstore, vec![0; 32])
+        .await
+        .unwrap();
+    let a = ["root".to_string(), "a.bin".to_string()];
+    let b = ["root".to_string(), "b.bin".to_string()];
+    helper.set_quota(Some(1000)).await.unwrap();
+    helper.write_file(&a, vec![0; 600], 0).await.unwrap();
+    let cid = helper.append_file(&a, vec![0; 300], 0).await.unwrap();
+
+    let helper = &mut PrivateDirectoryHelper::load_with_wnfs_key(blockstore, cid, vec![0; 32])
+        .await
+        .unwrap();
+    assert_eq!(helper.quota(), Some(1000));
+    let err = helper
+        .write_file_stream(&b, Cursor::new(vec![0; 200]), 0)
+        .await
+        .unwrap_err();
+    assert_eq!(
+        err,
+        WnfsUtilsError::QuotaExceeded {
+            quota_bytes: 1000,
+            used_bytes: 900,
+            requested_bytes: 200,
+        }
+    );
+    assert!(!helper.exists(&b).await.unwrap());
+
+    // Removing files frees their bytes, and the quota record isn't one of the user's files.
+    helper.rm(&a).await.unwrap();
+    helper
+        .write_file_stream(&b, Cursor::new(vec![0; 200]), 0)
+        .await
+        .unwrap();
+    let names: Vec<String> = helper
+        .ls_files(&[])
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|(name, _)| name)
+        .collect();
+    assert_eq!(names, vec!["root".to_string()]);
+}
@@ -0,0 +1,206 @@
+//! Recursive walk over the private tree as an async stream.
+
+use futures::stream::{self, LocalBoxStream, StreamExt};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::rc::Rc;
+use wnfs::private::{PrivateDirectory, PrivateNode};
+
+use crate::error::WnfsUtilsError;
+use crate::private_forest::{wnfs_error, PrivateDirectoryHelper};
+use crate::stat::DirEntry;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WalkOrder {
+    /// Every directory is followed by its contents, in name order.
+    #[default]
+    DepthFirst,
+    /// All entries of a depth come before the entries of the next depth.
+    BreadthFirst,
+}
+
+/// Options of `PrivateDirectoryHelper::walk`. Globs are matched against the path relative
+/// to the walked directory, e.g. `photos/2023/beach.jpg`, and `*` also matches `/`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WalkOptions {
+    /// Deepest level to walk, where the direct children of the walked directory are at 1.
+    pub max_depth: Option<usize>,
+    /// If not empty, only entries matching one of these globs are yielded. Directories
+    /// are still walked into.
+    pub include: Vec<String>,
+    /// Entries matching one of these globs are skipped, directories with all their contents.
+    pub exclude: Vec<String>,
+    pub order: WalkOrder,
+}
+
+// State of a walk. Entries are queued by name with their parent directory, and only loaded
+// from the forest when they are reached and not excluded.
+struct Walker<'s, 'a> {
+    helper: &'s PrivateDirectoryHelper<'a>,
+    root_segments: Vec<String>,
+    max_depth: Option<usize>,
+    include: Option<GlobSet>,
+    exclude: GlobSet,
+    order: WalkOrder,
+    pending: VecDeque<(Rc<PrivateDirectory>, Vec<String>, usize)>,
+    started: bool,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Implementations
+//--------------------------------------------------------------------------------------------------
+
+impl<'a> PrivateDirectoryHelper<'a> {
+    /// Walks the directory at `path_segments` recursively, yielding the path and entry of
+    /// every file and directory below it. The stream ends after the first error.
+    pub fn walk<'s>(
+        &'s self,
+        path_segments: &[String],
+        options: WalkOptions,
+    ) -> LocalBoxStream<'s, Result<(Vec<String>, DirEntry), WnfsUtilsError>> {
+        let walker = Walker::new(self, path_segments.to_vec(), options);
+        stream::unfold(Some(walker), |walker| async move {
+            let mut walker = match walker? {
+                Ok(walker) => walker,
+                Err(err) => return Some((Err(err), None)),
+            };
+            match walker.next_entry().await {
+                Ok(Some(entry)) => Some((Ok(entry), Some(Ok(walker)))),
+                Ok(None) => None,
+                Err(err) => Some((Err(err), None)),
+            }
+        })
+        .boxed_local()
+    }
+
+    /// Collects all entries of `walk`.
+    pub async fn walk_async(
+        &mut self,
+        path_segments: &[String],
+        options: WalkOptions,
+    ) -> Result<Vec<(Vec<String>, DirEntry)>, WnfsUtilsError> {
+        let mut entries = Vec::new();
+        let mut walk = self.walk(path_segments, options);
+        while let Some(entry) = walk.next().await {
+            entries.push(entry?);
+        }
+        Ok(entries)
+    }
+}
+
+impl<'s, 'a> Walker<'s, 'a> {
+    fn new(
+        helper: &'s PrivateDirectoryHelper<'a>,
+        root_segments: Vec<String>,
+        options: WalkOptions,
+    ) -> Result<Self, WnfsUtilsError> {
+        let include = match options.include.is_empty() {
+            true => None,
+            false => Some(build_glob_set(&options.include)?),
+        };
+        Ok(Self {
+            helper,
+            root_segments,
+            max_depth: options.max_depth,
+            include,
+            exclude: build_glob_set(&options.exclude)?,
+            order: options.order,
+            pending: VecDeque::new(),
+            started: false,
+        })
+    }
+
+    async fn next_entry(&mut self) -> Result<Option<(Vec<String>, DirEntry)>, WnfsUtilsError> {
+        if !self.started {
+            self.started = true;
+            let root_dir = self
+                .helper
+                .get_node(&self.root_segments)
+                .await
+                .map_err(wnfs_error("walk"))?
+                .as_dir()
+                .map_err(wnfs_error("walk"))?;
+            self.push_children(root_dir, self.root_segments.to_owned(), 1);
+        }
+        loop {
+            let next = match self.order {
+                WalkOrder::DepthFirst => self.pending.pop_back(),
+                WalkOrder::BreadthFirst => self.pending.pop_front(),
+            };
+            let Some((parent, path_segments, depth)) = next else {
+                return Ok(None);
+            };
+            let relative_path = path_segments[self.root_segments.len()..].join("/");
+            if self.exclude.is_match(&relative_path) {
+                continue;
+            }
+            let name = path_segments.last().cloned().unwrap_or_default();
+            let node = parent
+                .lookup_node(&name, true, &self.helper.forest, &self.helper.store)
+                .await
+                .map_err(wnfs_error("walk"))?
+                .ok_or_else(|| WnfsUtilsError::NotFound(path_segments.join("/")))
+                .map_err(wnfs_error("walk"))?;
+            if let PrivateNode::Dir(dir) = &node {
+                self.push_children(Rc::clone(dir), path_segments.to_owned(), depth + 1);
+            }
+            if let Some(include) = &self.include {
+                if !include.is_match(&relative_path) {
+                    continue;
+                }
+            }
+            let stat = self.helper.node_stat(&node).await?;
+            return Ok(Some((path_segments, DirEntry::new(name, stat))));
+        }
+    }
+
+    // Queues the names of the children of `dir` at `depth` so that they are visited in name
+    // order. The children themselves are loaded when they are visited.
+    fn push_children(
+        &mut self,
+        dir: Rc<PrivateDirectory>,
+        dir_segments: Vec<String>,
+        depth: usize,
+    ) {
+        if self.max_depth.is_some_and(|max_depth| depth > max_depth) {
+            return;
+        }
+        let children: Vec<_> = dir
+            .get_entries()
+            .map(|name| {
+                let mut path_segments = dir_segments.to_owned();
+                path_segments.push(name.to_owned());
+                (Rc::clone(&dir), path_segments, depth)
+            })
+            .collect();
+        match self.order {
+            WalkOrder::DepthFirst => self.pending.extend(children.into_iter().rev()),
+            WalkOrder::BreadthFirst => self.pending.extend(children),
+        }
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Functions
+//--------------------------------------------------------------------------------------------------
+
+fn build_glob_set(globs: &[String]) -> Result<GlobSet, WnfsUtilsError> {
+    let mut builder = GlobSetBuilder::new();
+    for glob in globs {
+        builder.add(
+            Glob::new(glob)
+                .map_err(|e| WnfsUtilsError::Other(e.to_string()))
+                .map_err(wnfs_error("walk"))?,
+        );
+    }
+    builder
+        .build()
+        .map_err(|e| WnfsUtilsError::Other(e.to_string()))
+        .map_err(wnfs_error("walk"))
+}
+
+#[cfg(test)]
+mod walk_tests;
@@ -0,0 +1,96 @@
+use futures::StreamExt;
+
+use crate::blockstore::FFIFriendlyBlockStore;
+use crate::error::WnfsUtilsError;
+use crate::memorystore::MemoryBlockStore;
+use crate::private_forest::PrivateDirectoryHelper;
+use crate::stat::NodeKind;
+use crate::walk::{WalkOptions, WalkOrder};
+
+async fn walked_paths(helper: &PrivateDirectoryHelper<'_>, options: WalkOptions) -> Vec<String> {
+    let mut paths = Vec::new();
+    let mut walk = helper.walk(&["root".into()], options);
+    while let Some(entry) = walk.next().await {
+        let (path_segments, _) = entry.unwrap();
+        paths.push(path_segments[1..].join("/"));
+    }
+    paths
+}
+
+#[tokio::test]
+async fn walk_orders_and_filters() {
+    let store = MemoryBlockStore::new();
+    let blockstore = &mut FFIFriendlyBlockStore::new(Box::new(store));
+    let (helper, _, _) = &mut PrivateDirectoryHelper::init(blockstore, vec![0; 32])
+        .await
+        .unwrap();
+    for path in ["a/x.txt", "a/b/y.txt", "a/b/z.jpg", "c.txt"] {
+        let path_segments = PrivateDirectoryHelper::parse_path(format!("root/{}", path));
+        helper
+            .write_file(&path_segments, b"data".to_vec(), 0)
+            .await
+            .unwrap();
+    }
+
+    let paths = walked_paths(helper, WalkOptions::default()).await;
+    assert_eq!(
+        paths,
+        vec!["a", "a/b", "a/b/y.txt", "a/b/z.jpg", "a/x.txt", "c.txt"]
+    );
+
+    let options = WalkOptions {
+        order: WalkOrder::BreadthFirst,
+        ..Default::default()
+    };
+    let paths = walked_paths(helper, options).await;
+    assert_eq!(
+        paths,
+        vec!["a", "c.txt", "a/b", "a/x.txt", "a/b/y.txt", "a/b/z.jpg"]
+    );
+
+    let options = WalkOptions {
+        max_depth: Some(2),
+        ..Default::default()
+    };
+    let paths = walked_paths(helper, options).await;
+    assert_eq!(paths, vec!["a", "a/b", "a/x.txt", "c.txt"]);
+
+    let options = WalkOptions {
+        include: vec!["*.txt".into()],
+        exclude: vec!["a/b".into()],
+        ..Default::default()
+    };
+    let paths = walked_paths(helper, options).await;
+    assert_eq!(paths, vec!["a/x.txt", "c.txt"]);
+
+    let entries = helper
+        .walk_async(&["root".into(), "a".into()], WalkOptions::default())
+        .await
+        .unwrap();
+    assert_eq!(entries.len(), 4);
+    assert_eq!(entries[0].1.kind, NodeKind::Dir);
+    assert_eq!(entries[1].1.name, "y.txt");
+    assert_eq!(entries[1].1.size, Some(4));
+}
+
+#[tokio::test]
+async fn walk_reports_errors() {
+    let store = MemoryBlockStore::new();
+    let blockstore = &mut FFIFriendlyBlockStore::new(Box::new(store));
+    let (helper, _, _) = &mut PrivateDirectoryHelper::init(blockstore, vec![0; 32])
+        .await
+        .unwrap();
+
+    let err = helper
+        .walk_async(&["missing".into()], WalkOptions::default())
+        .await
+        .unwrap_err();
+    assert!(matches!(err, WnfsUtilsError::NotFound(_)));
+
+    let options = WalkOptions {
+        include: vec!["[".into()],
+        ..Default::default()
+    };
+    let err = helper.walk_async(&[], options).await.unwrap_err();
+    assert!(matches!(err, WnfsUtilsError::Other(_)));
+}
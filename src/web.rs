@@ -0,0 +1,864 @@
+//! JavaScript bindings over `PrivateDirectoryHelper` for use with wasm-pack.
+//! All operations return Promises, CIDs cross the boundary as strings and
+//! metadata as plain JS objects.
+
+use std::rc::Rc;
+
+use futures::lock::Mutex;
+use js_sys::{Array, Function, Object, Promise, Reflect};
+use libipld::{Cid, Ipld};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::future_to_promise;
+use wnfs::common::Metadata;
+
+use crate::blockstore::FFIFriendlyBlockStore;
+use crate::error::WnfsUtilsError;
+use crate::jsstore::JsBlockStore;
+use crate::kdf::KdfParams;
+use crate::memorystore::MemoryBlockStore;
+use crate::merge::ConflictPolicy;
+use crate::private_forest::{PrivateDirectoryHelper, WnfsSession};
+use crate::stat::NodeKind;
+use crate::walk::WalkOptions;
+
+/// Block store handle passed from JS into `WnfsHelper::init` and `WnfsHelper::load_with_wnfs_key`.
+#[wasm_bindgen]
+#[derive(Clone)]
+pub struct WnfsBlockStore {
+    store: FFIFriendlyBlockStore<'static>,
+}
+
+#[wasm_bindgen]
+#[derive(Clone)]
+pub struct WnfsHelper {
+    helper: Rc<Mutex<PrivateDirectoryHelper<'static>>>,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Implementations
+//--------------------------------------------------------------------------------------------------
+
+impl From<FFIFriendlyBlockStore<'static>> for WnfsBlockStore {
+    fn from(store: FFIFriendlyBlockStore<'static>) -> Self {
+        Self { store }
+    }
+}
+
+#[wasm_bindgen]
+impl WnfsBlockStore {
+    /// Creates a block store that forwards to the given JS functions.
+    /// `get_block(cid: Uint8Array): Promise<Uint8Array>`,
+    /// `put_block(cid: Uint8Array, bytes: Uint8Array): Promise<void>`.
+    pub fn from_callbacks(get_block: Function, put_block: Function) -> WnfsBlockStore {
+        let store = JsBlockStore::new(get_block, put_block);
+        FFIFriendlyBlockStore::new(Box::new(store)).into()
+    }
+
+    /// Creates an in-memory block store, optionally limited to `capacity_bytes` of block data.
+    pub fn memory(capacity_bytes: Option<usize>) -> WnfsBlockStore {
+        let store = match capacity_bytes {
+            Some(capacity_bytes) => MemoryBlockStore::with_capacity_limit(capacity_bytes),
+            None => MemoryBlockStore::new(),
+        };
+        FFIFriendlyBlockStore::new(Box::new(store)).into()
+    }
+}
+
+#[wasm_bindgen]
+impl WnfsHelper {
+    /// Creates a new private forest and root directory. Resolves to `{ helper, cid }`.
+    pub fn init(store: &WnfsBlockStore, wnfs_key: Vec<u8>) -> Promise {
+        let mut store = store.store.to_owned();
+        future_to_promise(async move {
+            let (helper, _, cid) = PrivateDirectoryHelper::init_async(&mut store, wnfs_key)
+                .await
+                .map_err(to_js_error)?;
+            let result = Object::new();
+            Reflect::set(&result, &"helper".into(), &WnfsHelper::new(helper).into())?;
+            Reflect::set(&result, &"cid".into(), &cid.to_string().into())?;
+            Ok(result.into())
+        })
+    }
+
+    /// Loads the private forest at `cid` using the given wnfs key. Resolves to a `WnfsHelper`.
+    pub fn load_with_wnfs_key(store: &WnfsBlockStore, cid: String, wnfs_key: Vec<u8>) -> Promise {
+        let mut store = store.store.to_owned();
+        future_to_promise(async move {
+            let forest_cid = parse_cid(&cid)?;
+            let helper =
+                PrivateDirectoryHelper::load_with_wnfs_key_async(&mut store, forest_cid, wnfs_key)
+                    .await
+                    .map_err(to_js_error)?;
+            Ok(WnfsHelper::new(helper).into())
+        })
+    }
+
+    /// Opens the directory shared with the owner of `wnfs_key` by `sharer_did` in the forest at
+    /// `cid`, under share `counter` or the latest share if omitted. Unless `writable` is set,
+    /// changes fail with `ReadOnly`, and opening it writable fails with `ReadOnly` unless the
+    /// sharer granted it. Resolves to a `WnfsHelper` rooted at the shared directory.
+    pub fn receive_share(
+        store: &WnfsBlockStore,
+        sharer_did: String,
+        counter: Option<f64>,
+        cid: String,
+        wnfs_key: Vec<u8>,
+        writable: bool,
+    ) -> Promise {
+        let mut store = store.store.to_owned();
+        future_to_promise(async move {
+            let forest_cid = parse_cid(&cid)?;
+            let helper = PrivateDirectoryHelper::receive_share_async(
+                &mut store,
+                &sharer_did,
+                counter.map(|counter| counter as u64),
+                forest_cid,
+                wnfs_key,
+                writable,
+            )
+            .await
+            .map_err(to_js_error)?;
+            Ok(WnfsHelper::new(helper).into())
+        })
+    }
+
+    /// Creates a new drive whose wnfs key is derived from a secret of any length with Argon2id.
+    /// Resolves to `{ helper, cid, kdf_params_cid }`; keep `kdf_params_cid` next to the forest cid.
+    pub fn init_with_secret(store: &WnfsBlockStore, secret: Vec<u8>) -> Promise {
+        let store = store.store.to_owned();
+        future_to_promise(async move {
+            let (mut session, params_cid) =
+                WnfsSession::with_secret(store, &secret, KdfParams::argon2id())
+                    .await
+                    .map_err(to_js_error)?;
+            let (helper, _, cid) = session.init().await.map_err(to_js_error)?;
+            let result = Object::new();
+            Reflect::set(&result, &"helper".into(), &WnfsHelper::new(helper).into())?;
+            Reflect::set(&result, &"cid".into(), &cid.to_string().into())?;
+            Reflect::set(
+                &result,
+                &"kdf_params_cid".into(),
+                &params_cid.to_string().into(),
+            )?;
+            Ok(result.into())
+        })
+    }
+
+    /// Loads the private forest at `cid` with a wnfs key derived from `secret`.
+    /// Resolves to a `WnfsHelper`.
+    pub fn load_with_secret(
+        store: &WnfsBlockStore,
+        cid: String,
+        kdf_params_cid: String,
+        secret: Vec<u8>,
+    ) -> Promise {
+        let store = store.store.to_owned();
+        future_to_promise(async move {
+            let forest_cid = parse_cid(&cid)?;
+            let params_cid = parse_cid(&kdf_params_cid)?;
+            let mut session = WnfsSession::open_with_secret(store, &secret, &params_cid)
+                .await
+                .map_err(to_js_error)?;
+            let helper = session.load(forest_cid).await.map_err(to_js_error)?;
+            Ok(WnfsHelper::new(helper).into())
+        })
+    }
+
+    /// Writes `content` to `path`. `modification_time_seconds` is a plain number of seconds
+    /// since the unix epoch, or 0 for now. Resolves to the new forest cid.
+    pub fn write_file(
+        &self,
+        path: String,
+        content: Vec<u8>,
+        modification_time_seconds: f64,
+    ) -> Promise {
+        let helper = Rc::clone(&self.helper);
+        future_to_promise(async move {
+            let path_segments = PrivateDirectoryHelper::parse_path(path);
+            let cid = helper
+                .lock()
+                .await
+                .write_file_async(&path_segments, content, modification_time_seconds as i64)
+                .await
+                .map_err(to_js_error)?;
+            Ok(cid.to_string().into())
+        })
+    }
+
+    /// Appends `content` to the existing file at `path`. Resolves to the new forest cid.
+    pub fn append_file(
+        &self,
+        path: String,
+        content: Vec<u8>,
+        modification_time_seconds: f64,
+    ) -> Promise {
+        let helper = Rc::clone(&self.helper);
+        future_to_promise(async move {
+            let path_segments = PrivateDirectoryHelper::parse_path(path);
+            let cid = helper
+                .lock()
+                .await
+                .append_file_async(&path_segments, content, modification_time_seconds as i64)
+                .await
+                .map_err(to_js_error)?;
+            Ok(cid.to_string().into())
+        })
+    }
+
+    /// Overwrites the existing file at `path` with `content` starting at byte `offset`.
+    /// Resolves to the new forest cid.
+    pub fn write_at(
+        &self,
+        path: String,
+        offset: usize,
+        content: Vec<u8>,
+        modification_time_seconds: f64,
+    ) -> Promise {
+        let helper = Rc::clone(&self.helper);
+        future_to_promise(async move {
+            let path_segments = PrivateDirectoryHelper::parse_path(path);
+            let cid = helper
+                .lock()
+                .await
+                .write_at_async(
+                    &path_segments,
+                    offset,
+                    content,
+                    modification_time_seconds as i64,
+                )
+                .await
+                .map_err(to_js_error)?;
+            Ok(cid.to_string().into())
+        })
+    }
+
+    /// Reads the file at `path`. Resolves to a `Uint8Array`.
+    pub fn read_file(&self, path: String) -> Promise {
+        let helper = Rc::clone(&self.helper);
+        future_to_promise(async move {
+            let path_segments = PrivateDirectoryHelper::parse_path(path);
+            let content = helper
+                .lock()
+                .await
+                .read_file_async(&path_segments)
+                .await
+                .map_err(to_js_error)?;
+            Ok(js_sys::Uint8Array::from(content.as_slice()).into())
+        })
+    }
+
+    /// Reads up to `len` bytes of the file at `path` starting at byte `offset`.
+    /// Resolves to a `Uint8Array`.
+    pub fn read_file_range(&self, path: String, offset: usize, len: usize) -> Promise {
+        let helper = Rc::clone(&self.helper);
+        future_to_promise(async move {
+            let path_segments = PrivateDirectoryHelper::parse_path(path);
+            let content = helper
+                .lock()
+                .await
+                .read_file_range_async(&path_segments, offset, len)
+                .await
+                .map_err(to_js_error)?;
+            Ok(js_sys::Uint8Array::from(content.as_slice()).into())
+        })
+    }
+
+    /// Creates the directory at `path`. Resolves to the new forest cid.
+    pub fn mkdir(&self, path: String) -> Promise {
+        let helper = Rc::clone(&self.helper);
+        future_to_promise(async move {
+            let path_segments = PrivateDirectoryHelper::parse_path(path);
+            let cid = helper
+                .lock()
+                .await
+                .mkdir_async(&path_segments)
+                .await
+                .map_err(to_js_error)?;
+            Ok(cid.to_string().into())
+        })
+    }
+
+    /// Removes the file or directory at `path`. Resolves to the new forest cid.
+    pub fn rm(&self, path: String) -> Promise {
+        let helper = Rc::clone(&self.helper);
+        future_to_promise(async move {
+            let path_segments = PrivateDirectoryHelper::parse_path(path);
+            let cid = helper
+                .lock()
+                .await
+                .rm_async(&path_segments)
+                .await
+                .map_err(to_js_error)?;
+            Ok(cid.to_string().into())
+        })
+    }
+
+    /// Moves `source` to `target`. Resolves to the new forest cid.
+    pub fn mv(&self, source: String, target: String) -> Promise {
+        let helper = Rc::clone(&self.helper);
+        future_to_promise(async move {
+            let source_path_segments = PrivateDirectoryHelper::parse_path(source);
+            let target_path_segments = PrivateDirectoryHelper::parse_path(target);
+            let cid = helper
+                .lock()
+                .await
+                .mv_async(&source_path_segments, &target_path_segments)
+                .await
+                .map_err(to_js_error)?;
+            Ok(cid.to_string().into())
+        })
+    }
+
+    /// Copies `source` to `target`. Resolves to the new forest cid.
+    pub fn cp(&self, source: String, target: String) -> Promise {
+        let helper = Rc::clone(&self.helper);
+        future_to_promise(async move {
+            let source_path_segments = PrivateDirectoryHelper::parse_path(source);
+            let target_path_segments = PrivateDirectoryHelper::parse_path(target);
+            let cid = helper
+                .lock()
+                .await
+                .cp_async(&source_path_segments, &target_path_segments)
+                .await
+                .map_err(to_js_error)?;
+            Ok(cid.to_string().into())
+        })
+    }
+
+    /// Lists the directory at `path`. Resolves to an array of `{ name, metadata }` objects.
+    pub fn ls(&self, path: String) -> Promise {
+        let helper = Rc::clone(&self.helper);
+        future_to_promise(async move {
+            let path_segments = PrivateDirectoryHelper::parse_path(path);
+            let entries = helper
+                .lock()
+                .await
+                .ls_files_async(&path_segments)
+                .await
+                .map_err(to_js_error)?;
+            let result = Array::new();
+            for (name, metadata) in entries.iter() {
+                let entry = Object::new();
+                Reflect::set(&entry, &"name".into(), &name.into())?;
+                Reflect::set(&entry, &"metadata".into(), &metadata_to_js(metadata)?)?;
+                result.push(&entry);
+            }
+            Ok(result.into())
+        })
+    }
+
+    /// Lists one page of the directory at `path`. Resolves to `{ entries, cursor }`, where each
+    /// entry is `{ name, kind, size, modified, created }`; pass `cursor` to get the next page.
+    pub fn ls_detailed(&self, path: String, cursor: Option<String>, limit: usize) -> Promise {
+        let helper = Rc::clone(&self.helper);
+        future_to_promise(async move {
+            let path_segments = PrivateDirectoryHelper::parse_path(path);
+            let listing = helper
+                .lock()
+                .await
+                .ls_detailed_async(&path_segments, cursor.as_deref(), limit)
+                .await
+                .map_err(to_js_error)?;
+            to_js_value(&listing)
+        })
+    }
+
+    /// Walks the directory at `path` recursively. `options` is an optional
+    /// `{ max_depth, include, exclude, order }` object, with `order` either `"depth_first"` or
+    /// `"breadth_first"`. Resolves to an array of `{ path, entry }` objects.
+    pub fn walk(&self, path: String, options: JsValue) -> Promise {
+        let helper = Rc::clone(&self.helper);
+        future_to_promise(async move {
+            let path_segments = PrivateDirectoryHelper::parse_path(path);
+            let options: WalkOptions = match options.is_undefined() || options.is_null() {
+                true => WalkOptions::default(),
+                false => from_js_value(&options)?,
+            };
+            let entries = helper
+                .lock()
+                .await
+                .walk_async(&path_segments, options)
+                .await
+                .map_err(to_js_error)?;
+            let result = Array::new();
+            for (path_segments, entry) in entries.iter() {
+                let item = Object::new();
+                Reflect::set(&item, &"path".into(), &path_segments.join("/").into())?;
+                Reflect::set(&item, &"entry".into(), &to_js_value(entry)?)?;
+                result.push(&item);
+            }
+            Ok(result.into())
+        })
+    }
+
+    /// Resolves to `{ files, directories, logical_bytes, encrypted_bytes }` of the file or
+    /// directory at `path`.
+    pub fn du(&self, path: String) -> Promise {
+        let helper = Rc::clone(&self.helper);
+        future_to_promise(async move {
+            let path_segments = PrivateDirectoryHelper::parse_path(path);
+            let usage = helper
+                .lock()
+                .await
+                .du_async(&path_segments)
+                .await
+                .map_err(to_js_error)?;
+            to_js_value(&usage)
+        })
+    }
+
+    /// Limits the total size of all files of the drive; writes exceeding it are rejected.
+    /// Pass `undefined` to remove the limit. The quota is stored in the drive. Resolves to
+    /// the new forest cid.
+    pub fn set_quota(&self, quota_bytes: Option<f64>) -> Promise {
+        let helper = Rc::clone(&self.helper);
+        future_to_promise(async move {
+            let cid = helper
+                .lock()
+                .await
+                .set_quota_async(quota_bytes.map(|quota_bytes| quota_bytes as u64))
+                .await
+                .map_err(to_js_error)?;
+            Ok(cid.to_string().into())
+        })
+    }
+
+    /// Resolves to `{ kind, size, created, modified, content_block_count, metadata }` of the
+    /// file or directory at `path`. `kind` is `"file"` or `"dir"`.
+    pub fn stat(&self, path: String) -> Promise {
+        let helper = Rc::clone(&self.helper);
+        future_to_promise(async move {
+            let path_segments = PrivateDirectoryHelper::parse_path(path);
+            let stat = helper
+                .lock()
+                .await
+                .stat_async(&path_segments)
+                .await
+                .map_err(to_js_error)?;
+            let result = Object::new();
+            let kind = match stat.kind {
+                NodeKind::File => "file",
+                NodeKind::Dir => "dir",
+            };
+            Reflect::set(&result, &"kind".into(), &kind.into())?;
+            if let Some(size) = stat.size {
+                Reflect::set(&result, &"size".into(), &(size as f64).into())?;
+            }
+            if let Some(created) = stat.created {
+                Reflect::set(&result, &"created".into(), &(created as f64).into())?;
+            }
+            if let Some(modified) = stat.modified {
+                Reflect::set(&result, &"modified".into(), &(modified as f64).into())?;
+            }
+            Reflect::set(
+                &result,
+                &"content_block_count".into(),
+                &(stat.content_block_count as f64).into(),
+            )?;
+            Reflect::set(&result, &"metadata".into(), &to_js_value(&stat.metadata)?)?;
+            Ok(result.into())
+        })
+    }
+
+    /// Sets the custom metadata entry `key` of the file or directory at `path` to a
+    /// JSON-compatible `value`. Resolves to the new forest cid.
+    pub fn set_metadata(&self, path: String, key: String, value: JsValue) -> Promise {
+        let helper = Rc::clone(&self.helper);
+        future_to_promise(async move {
+            let path_segments = PrivateDirectoryHelper::parse_path(path);
+            let value: Ipld = from_js_value(&value)?;
+            let cid = helper
+                .lock()
+                .await
+                .set_metadata_async(&path_segments, &key, value)
+                .await
+                .map_err(to_js_error)?;
+            Ok(cid.to_string().into())
+        })
+    }
+
+    /// Resolves to an object with the custom metadata entries of the file or directory at `path`.
+    pub fn get_metadata(&self, path: String) -> Promise {
+        let helper = Rc::clone(&self.helper);
+        future_to_promise(async move {
+            let path_segments = PrivateDirectoryHelper::parse_path(path);
+            let metadata = helper
+                .lock()
+                .await
+                .get_metadata_async(&path_segments)
+                .await
+                .map_err(to_js_error)?;
+            to_js_value(&metadata)
+        })
+    }
+
+    /// Removes the custom metadata entry `key` of the file or directory at `path`.
+    /// Resolves to the new forest cid.
+    pub fn remove_metadata(&self, path: String, key: String) -> Promise {
+        let helper = Rc::clone(&self.helper);
+        future_to_promise(async move {
+            let path_segments = PrivateDirectoryHelper::parse_path(path);
+            let cid = helper
+                .lock()
+                .await
+                .remove_metadata_async(&path_segments, &key)
+                .await
+                .map_err(to_js_error)?;
+            Ok(cid.to_string().into())
+        })
+    }
+
+    /// Resolves to up to `limit` revisions `{ revision, kind, size, modified }` of the file or
+    /// directory at `path`, starting with the current one. `revision` is the cid identifying
+    /// the revision.
+    pub fn history(&self, path: String, limit: usize) -> Promise {
+        let helper = Rc::clone(&self.helper);
+        future_to_promise(async move {
+            let path_segments = PrivateDirectoryHelper::parse_path(path);
+            let revisions = helper
+                .lock()
+                .await
+                .history_async(&path_segments, limit)
+                .await
+                .map_err(to_js_error)?;
+            to_js_value(&revisions)
+        })
+    }
+
+    /// Reads the file at `path` as it was at `revision`. Resolves to a `Uint8Array`.
+    pub fn read_file_at_revision(&self, path: String, revision: String) -> Promise {
+        let helper = Rc::clone(&self.helper);
+        future_to_promise(async move {
+            let path_segments = PrivateDirectoryHelper::parse_path(path);
+            let content = helper
+                .lock()
+                .await
+                .read_file_at_revision_async(&path_segments, &revision)
+                .await
+                .map_err(to_js_error)?;
+            Ok(js_sys::Uint8Array::from(content.as_slice()).into())
+        })
+    }
+
+    /// Makes the whole drive look like it did at the forest `cid`, keeping later changes in
+    /// the history. Resolves to the new forest cid.
+    pub fn rollback_to(&self, cid: String) -> Promise {
+        let helper = Rc::clone(&self.helper);
+        future_to_promise(async move {
+            let forest_cid = parse_cid(&cid)?;
+            let cid = helper
+                .lock()
+                .await
+                .rollback_to_async(forest_cid)
+                .await
+                .map_err(to_js_error)?;
+            Ok(cid.to_string().into())
+        })
+    }
+
+    /// Replaces the file or directory at `path` with its state at the forest `cid`.
+    /// Resolves to the new forest cid.
+    pub fn restore_path_from(&self, cid: String, path: String) -> Promise {
+        let helper = Rc::clone(&self.helper);
+        future_to_promise(async move {
+            let forest_cid = parse_cid(&cid)?;
+            let path_segments = PrivateDirectoryHelper::parse_path(path);
+            let cid = helper
+                .lock()
+                .await
+                .restore_path_from_async(forest_cid, &path_segments)
+                .await
+                .map_err(to_js_error)?;
+            Ok(cid.to_string().into())
+        })
+    }
+
+    /// Resolves to the changes below `path` between two forest cids, as an array of
+    /// `{ path_segments, change, kind }`.
+    pub fn diff(&self, old_cid: String, new_cid: String, path: String) -> Promise {
+        let helper = Rc::clone(&self.helper);
+        future_to_promise(async move {
+            let old_forest_cid = parse_cid(&old_cid)?;
+            let new_forest_cid = parse_cid(&new_cid)?;
+            let path_segments = PrivateDirectoryHelper::parse_path(path);
+            let changes = helper
+                .lock()
+                .await
+                .diff_async(old_forest_cid, new_forest_cid, &path_segments)
+                .await
+                .map_err(to_js_error)?;
+            to_js_value(&changes)
+        })
+    }
+
+    /// Merges the changes made in `ours_cid` and `theirs_cid` since their common ancestor
+    /// `base_cid`. `policy` is `"keep_both"` (the default), `"last_writer_wins"` or `"fail"`.
+    /// Resolves to the merged forest cid.
+    pub fn merge(
+        &self,
+        base_cid: String,
+        ours_cid: String,
+        theirs_cid: String,
+        policy: JsValue,
+    ) -> Promise {
+        let helper = Rc::clone(&self.helper);
+        future_to_promise(async move {
+            let base_forest_cid = parse_cid(&base_cid)?;
+            let ours_forest_cid = parse_cid(&ours_cid)?;
+            let theirs_forest_cid = parse_cid(&theirs_cid)?;
+            let policy: ConflictPolicy = match policy.is_undefined() || policy.is_null() {
+                true => ConflictPolicy::default(),
+                false => from_js_value(&policy)?,
+            };
+            let cid = helper
+                .lock()
+                .await
+                .merge_async(base_forest_cid, ours_forest_cid, theirs_forest_cid, policy)
+                .await
+                .map_err(to_js_error)?;
+            Ok(cid.to_string().into())
+        })
+    }
+
+    /// Resolves to the DID of this drive's owner and the public exchange key derived from the
+    /// wnfs key as `{ did, exchange_public_key }`, for other users that want to share with them.
+    pub fn identity(&self) -> Promise {
+        let helper = Rc::clone(&self.helper);
+        future_to_promise(async move {
+            let helper = helper.lock().await;
+            let exchange_public_key = helper.exchange_public_key().map_err(to_js_error)?;
+            let result = Object::new();
+            let did = helper.did().map_err(to_js_error)?;
+            Reflect::set(&result, &"did".into(), &did.into())?;
+            Reflect::set(
+                &result,
+                &"exchange_public_key".into(),
+                &js_sys::Uint8Array::from(exchange_public_key.as_slice()).into(),
+            )?;
+            Ok(result.into())
+        })
+    }
+
+    /// Shares the directory at `path` with the owner of `recipient_exchange_public_key`, who
+    /// may open it writable if `writable` is set. Resolves to `{ counter, cid }`, where
+    /// `counter` is handed to the recipient.
+    pub fn share_path(
+        &self,
+        path: String,
+        recipient_exchange_public_key: Vec<u8>,
+        recipient_did: String,
+        writable: bool,
+    ) -> Promise {
+        let helper = Rc::clone(&self.helper);
+        future_to_promise(async move {
+            let path_segments = PrivateDirectoryHelper::parse_path(path);
+            let (counter, cid) = helper
+                .lock()
+                .await
+                .share_path_async(
+                    &path_segments,
+                    &recipient_exchange_public_key,
+                    &recipient_did,
+                    writable,
+                )
+                .await
+                .map_err(to_js_error)?;
+            let result = Object::new();
+            Reflect::set(&result, &"counter".into(), &(counter as f64).into())?;
+            Reflect::set(&result, &"cid".into(), &cid.to_string().into())?;
+            Ok(result.into())
+        })
+    }
+
+    /// Revokes the shares of the directory at `path` with `recipient_did`, or all of
+    /// its shares if omitted, and shares it again with the remaining recipients.
+    /// Resolves to the new forest cid.
+    pub fn revoke(&self, path: String, recipient_did: Option<String>) -> Promise {
+        let helper = Rc::clone(&self.helper);
+        future_to_promise(async move {
+            let path_segments = PrivateDirectoryHelper::parse_path(path);
+            let mut helper = helper.lock().await;
+            let cid = match recipient_did {
+                Some(recipient_did) => {
+                    helper
+                        .revoke_recipient_async(&path_segments, &recipient_did)
+                        .await
+                }
+                None => helper.revoke_async(&path_segments).await,
+            }
+            .map_err(to_js_error)?;
+            Ok(cid.to_string().into())
+        })
+    }
+
+    /// Resolves to the shares of this drive, as an array of
+    /// `{ path_segments, recipient_did, recipient_exchange_public_key, counter }`.
+    pub fn list_shares(&self) -> Promise {
+        let helper = Rc::clone(&self.helper);
+        future_to_promise(async move {
+            let shares = helper
+                .lock()
+                .await
+                .list_shares_async()
+                .await
+                .map_err(to_js_error)?;
+            to_js_value(&shares)
+        })
+    }
+
+    /// Resolves to whether a file or directory exists at `path`.
+    pub fn exists(&self, path: String) -> Promise {
+        let helper = Rc::clone(&self.helper);
+        future_to_promise(async move {
+            let path_segments = PrivateDirectoryHelper::parse_path(path);
+            let exists = helper
+                .lock()
+                .await
+                .exists_async(&path_segments)
+                .await
+                .map_err(to_js_error)?;
+            Ok(exists.into())
+        })
+    }
+
+    /// Starts a resumable upload into `path`. Resolves to `{ upload_id, cid }`.
+    pub fn begin_upload(&self, path: String, modification_time_seconds: f64) -> Promise {
+        let helper = Rc::clone(&self.helper);
+        future_to_promise(async move {
+            let path_segments = PrivateDirectoryHelper::parse_path(path);
+            let (upload_id, cid) = helper
+                .lock()
+                .await
+                .begin_upload_async(&path_segments, modification_time_seconds as i64)
+                .await
+                .map_err(to_js_error)?;
+            let result = Object::new();
+            Reflect::set(&result, &"upload_id".into(), &upload_id.into())?;
+            Reflect::set(&result, &"cid".into(), &cid.to_string().into())?;
+            Ok(result.into())
+        })
+    }
+
+    /// Stores chunk `index` of an upload. Resolves to the new forest cid.
+    pub fn upload_chunk(&self, upload_id: String, index: u32, content: Vec<u8>) -> Promise {
+        let helper = Rc::clone(&self.helper);
+        future_to_promise(async move {
+            let cid = helper
+                .lock()
+                .await
+                .upload_chunk_async(&upload_id, index.into(), content)
+                .await
+                .map_err(to_js_error)?;
+            Ok(cid.to_string().into())
+        })
+    }
+
+    /// Resolves to `{ path, chunks_received }` of an unfinished upload.
+    pub fn upload_status(&self, upload_id: String) -> Promise {
+        let helper = Rc::clone(&self.helper);
+        future_to_promise(async move {
+            let status = helper
+                .lock()
+                .await
+                .upload_status_async(&upload_id)
+                .await
+                .map_err(to_js_error)?;
+            let chunks_received = Array::new();
+            for index in status.chunks_received {
+                chunks_received.push(&(index as f64).into());
+            }
+            let result = Object::new();
+            Reflect::set(
+                &result,
+                &"path".into(),
+                &status.session.path_segments.join("/").into(),
+            )?;
+            Reflect::set(&result, &"chunks_received".into(), &chunks_received)?;
+            Ok(result.into())
+        })
+    }
+
+    /// Writes chunks `0..chunk_count` of an upload into its target file.
+    /// Resolves to the new forest cid.
+    pub fn finish_upload(&self, upload_id: String, chunk_count: u32) -> Promise {
+        let helper = Rc::clone(&self.helper);
+        future_to_promise(async move {
+            let cid = helper
+                .lock()
+                .await
+                .finish_upload_async(&upload_id, chunk_count.into())
+                .await
+                .map_err(to_js_error)?;
+            Ok(cid.to_string().into())
+        })
+    }
+
+    /// Discards an upload and its chunks. Resolves to the new forest cid.
+    pub fn abort_upload(&self, upload_id: String) -> Promise {
+        let helper = Rc::clone(&self.helper);
+        future_to_promise(async move {
+            let cid = helper
+                .lock()
+                .await
+                .abort_upload_async(&upload_id)
+                .await
+                .map_err(to_js_error)?;
+            Ok(cid.to_string().into())
+        })
+    }
+}
+
+impl WnfsHelper {
+    fn new(helper: PrivateDirectoryHelper<'static>) -> Self {
+        Self {
+            helper: Rc::new(Mutex::new(helper)),
+        }
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Functions
+//--------------------------------------------------------------------------------------------------
+
+// Converts an error into a JS `Error` carrying the stable numeric `code` of `WnfsUtilsError`.
+fn to_js_error(err: WnfsUtilsError) -> JsValue {
+    let js_error = js_sys::Error::new(&err.to_string());
+    let _ = Reflect::set(&js_error, &"code".into(), &err.code().into());
+    js_error.into()
+}
+
+fn parse_cid(cid: &str) -> Result<Cid, JsValue> {
+    Cid::try_from(cid).map_err(|e| to_js_error(WnfsUtilsError::Other(e.to_string())))
+}
+
+// Converts a serializable value into a plain JS value by way of JSON.
+fn to_js_value<T: serde::Serialize>(value: &T) -> Result<JsValue, JsValue> {
+    let json = serde_json::to_string(value)
+        .map_err(|e| to_js_error(WnfsUtilsError::Other(e.to_string())))?;
+    js_sys::JSON::parse(&json)
+}
+
+// Converts a plain JS value into a deserializable value by way of JSON.
+fn from_js_value<T: serde::de::DeserializeOwned>(value: &JsValue) -> Result<T, JsValue> {
+    let json: String = js_sys::JSON::stringify(value)?.into();
+    serde_json::from_str(&json).map_err(|e| to_js_error(WnfsUtilsError::Other(e.to_string())))
+}
+
+fn metadata_to_js(metadata: &Metadata) -> Result<JsValue, JsValue> {
+    let result = Object::new();
+    if let Some(created) = metadata.get_created() {
+        Reflect::set(
+            &result,
+            &"created".into(),
+            &(created.timestamp() as f64).into(),
+        )?;
+    }
+    if let Some(modified) = metadata.get_modified() {
+        Reflect::set(
+            &result,
+            &"modified".into(),
+            &(modified.timestamp() as f64).into(),
+        )?;
+    }
+    Ok(result.into())
+}